@@ -0,0 +1,267 @@
+mod phases;
+mod snapshot;
+
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use phases::{Phase, PHASES};
+use snapshot::Snapshot;
+
+/// Rounds after which the fixes are assumed to go back and forth.
+const DEFAULT_MAX_ROUNDS: usize = 10;
+
+const USAGE: &str = "\
+Applies the fixes of mate phase by phase, until the lints suggest nothing more.
+
+Usage: cargo mate [--max-rounds <N>]
+
+Options:
+    --max-rounds <N>  stop after N rounds over the phases [default: 10]
+    -h, --help        print this message
+
+The workspace is checked with `cargo check` after the fixes of each phase, which are
+reverted if it doesn't compile.
+";
+
+/// What became of the fixes of a phase in a round.
+enum Outcome {
+    /// The phase had nothing to fix.
+    Unchanged,
+    /// The fixes of this many files compiled, and were kept.
+    Applied(usize),
+    /// The fixes of this many files broke the build, and were reverted.
+    Reverted(usize),
+}
+
+struct Batch {
+    round: usize,
+    phase: &'static str,
+    outcome: Outcome,
+}
+
+fn main() -> ExitCode {
+    // Run as `cargo mate`, the binary gets the name of the subcommand first.
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "mate") {
+        args.remove(0);
+    }
+    let max_rounds = match parse_args(&args) {
+        Ok(Some(max_rounds)) => max_rounds,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprint!("error: {message}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(max_rounds) {
+        Ok((batches, converged)) => {
+            print_summary(&batches, converged);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The maximum number of rounds, or `None` if the usage is asked for.
+fn parse_args(args: &[String]) -> Result<Option<usize>, String> {
+    let mut max_rounds = DEFAULT_MAX_ROUNDS;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--max-rounds" => {
+                max_rounds = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or("`--max-rounds` takes a positive number")?;
+            }
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    Ok(Some(max_rounds))
+}
+
+/// Applies the phases in order until a round changes nothing. Returns the batches, and
+/// whether the last round changed nothing.
+fn run(max_rounds: usize) -> io::Result<(Vec<Batch>, bool)> {
+    let root = workspace_root()?;
+    let mut batches = Vec::new();
+    for round in 1..=max_rounds {
+        let mut changed = false;
+        for phase in PHASES {
+            eprintln!("mate: round {round}, phase {}", phase.name);
+            let snapshot = Snapshot::take(&root)?;
+            let fixed = apply_fixes(&root, phase)?;
+            let files = snapshot.changed_files()?;
+            let outcome = if files.is_empty() {
+                if !fixed {
+                    return Err(io::Error::other(format!(
+                        "`cargo dylint --fix` failed in phase {}",
+                        phase.name
+                    )));
+                }
+                Outcome::Unchanged
+            } else if fixed && cargo_check(&root)? {
+                changed = true;
+                Outcome::Applied(files.len())
+            } else {
+                snapshot.restore(&files)?;
+                Outcome::Reverted(files.len())
+            };
+            batches.push(Batch {
+                round,
+                phase: phase.name,
+                outcome,
+            });
+        }
+        if !changed {
+            return Ok((batches, true));
+        }
+    }
+    Ok((batches, false))
+}
+
+fn workspace_root() -> io::Result<PathBuf> {
+    let output = Command::new(cargo())
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("no cargo workspace found"));
+    }
+    let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    manifest
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| io::Error::other("no cargo workspace found"))
+}
+
+/// Runs the lints of `phase` alone with `--fix`. Returns whether cargo succeeded.
+fn apply_fixes(root: &Path, phase: &Phase) -> io::Result<bool> {
+    let mut rustflags = env::var("DYLINT_RUSTFLAGS").unwrap_or_default();
+    if !rustflags.is_empty() {
+        rustflags.push(' ');
+    }
+    rustflags.push_str(&phase.rustflags());
+    let status = Command::new(cargo())
+        .current_dir(root)
+        .args(["dylint", "--all", "--workspace", "--fix"])
+        .args(["--", "--allow-dirty", "--allow-no-vcs"])
+        .env("DYLINT_RUSTFLAGS", rustflags)
+        .status()?;
+    Ok(status.success())
+}
+
+fn cargo_check(root: &Path) -> io::Result<bool> {
+    let status = Command::new(cargo())
+        .current_dir(root)
+        .args(["check", "--workspace", "--all-targets"])
+        .status()?;
+    Ok(status.success())
+}
+
+/// The cargo which runs the subcommand, so that its toolchain is used for the others.
+fn cargo() -> OsString {
+    env::var_os("CARGO").unwrap_or_else(|| "cargo".into())
+}
+
+fn print_summary(batches: &[Batch], converged: bool) {
+    println!();
+    println!("{:<7}{:<10}result", "round", "phase");
+    for batch in batches {
+        let result = match batch.outcome {
+            Outcome::Unchanged => "nothing to fix".to_string(),
+            Outcome::Applied(n) => format!("{} fixed", count(n, "file", "files")),
+            Outcome::Reverted(n) => format!(
+                "{} reverted, the fixes didn't compile",
+                count(n, "file", "files")
+            ),
+        };
+        println!("{:<7}{:<10}{result}", batch.round, batch.phase);
+    }
+
+    let applied = batches
+        .iter()
+        .filter(|batch| matches!(batch.outcome, Outcome::Applied(_)))
+        .count();
+    let reverted = batches
+        .iter()
+        .filter(|batch| matches!(batch.outcome, Outcome::Reverted(_)))
+        .count();
+    let rounds = count(
+        batches.last().map_or(0, |batch| batch.round),
+        "round",
+        "rounds",
+    );
+    println!();
+    if converged {
+        println!("nothing left to fix after {rounds}");
+    } else {
+        println!("stopped after {rounds}, the lints still have fixes");
+    }
+    println!(
+        "{} applied, {reverted} reverted",
+        count(applied, "batch", "batches")
+    );
+}
+
+fn count(n: usize, one: &str, many: &str) -> String {
+    if n == 1 {
+        format!("1 {one}")
+    } else {
+        format!("{n} {many}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, DEFAULT_MAX_ROUNDS};
+
+    fn parse(args: &[&str]) -> Result<Option<usize>, String> {
+        parse_args(&args.iter().map(ToString::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_the_max_rounds() {
+        assert_eq!(parse(&[]), Ok(Some(DEFAULT_MAX_ROUNDS)));
+        assert_eq!(parse(&["--max-rounds", "3"]), Ok(Some(3)));
+        assert_eq!(
+            parse(&["--max-rounds", "3", "--max-rounds", "5"]),
+            Ok(Some(5))
+        );
+    }
+
+    #[test]
+    fn asks_for_the_usage() {
+        assert_eq!(parse(&["--help"]), Ok(None));
+        assert_eq!(parse(&["--max-rounds", "3", "-h"]), Ok(None));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for args in [
+            &["--max-rounds"][..],
+            &["--max-rounds", "0"],
+            &["--max-rounds", "many"],
+        ] {
+            assert_eq!(
+                parse(args),
+                Err("`--max-rounds` takes a positive number".to_string())
+            );
+        }
+        assert_eq!(
+            parse(&["--fix"]),
+            Err("unexpected argument `--fix`".to_string())
+        );
+    }
+}
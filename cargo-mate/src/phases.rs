@@ -0,0 +1,148 @@
+/// Lints whose fixes are applied together, as one batch.
+pub(crate) struct Phase {
+    pub(crate) name: &'static str,
+    pub(crate) lints: &'static [&'static str],
+}
+
+/// The phases registered by the library in `src/lib.rs`, in the same order. A loop is
+/// turned into an iterator before it can be folded, and folded before it can be
+/// parallelized.
+pub(crate) const PHASES: &[Phase] = &[
+    Phase {
+        name: "to_iter",
+        lints: &["to_iter"],
+    },
+    Phase {
+        name: "fold",
+        lints: &["fold_simple", "fold_try"],
+    },
+    // The parallel code doesn't compile without the imports of `rayon_prelude`, so they
+    // are applied along with it.
+    Phase {
+        name: "par",
+        lints: &[
+            "warn_par_fold_simple",
+            "warn_par_fold_combine",
+            "warn_par_try_fold",
+            "warn_par_reduction",
+            "warn_par_counter",
+            "par_iter",
+            "par_join",
+            "par_divide_conquer",
+            "par_sort",
+            "rayon_prelude",
+        ],
+    },
+];
+
+impl Phase {
+    /// The flags for the lint driver which enable the lints of this phase only.
+    pub(crate) fn rustflags(&self) -> String {
+        PHASES
+            .iter()
+            .flat_map(|phase| phase.lints)
+            .map(|lint| {
+                if self.lints.contains(lint) {
+                    format!("-W {lint}")
+                } else {
+                    format!("-A {lint}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::PHASES;
+
+    /// Lints which no phase applies, as they have no fixes.
+    const LEFT_OUT: &[&str] = &["rayon_dependency"];
+
+    #[test]
+    fn rustflags_warn_for_the_lints_of_the_phase_only() {
+        for phase in PHASES {
+            let flags = phase.rustflags();
+            let flags: Vec<&str> = flags.split(' ').collect();
+            let lints: Vec<&str> = PHASES
+                .iter()
+                .flat_map(|phase| phase.lints)
+                .copied()
+                .collect();
+            assert_eq!(flags.len(), 2 * lints.len());
+            for (flag, lint) in flags.chunks(2).zip(lints) {
+                let level = if phase.lints.contains(&lint) {
+                    "-W"
+                } else {
+                    "-A"
+                };
+                assert_eq!(flag, [level, lint], "in phase {}", phase.name);
+            }
+        }
+    }
+
+    #[test]
+    fn every_registered_lint_is_in_one_phase() -> io::Result<()> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let library = fs::read_to_string(root.join("src/lib.rs"))?;
+        let registered = library
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("//"))
+            .filter_map(|line| line.strip_suffix("::register_lints(sess, lint_store);"));
+
+        let mut declared = Vec::new();
+        for krate in registered {
+            let mut files = Vec::new();
+            rust_files(&root.join("lints").join(krate).join("src"), &mut files)?;
+            for file in files {
+                declared.extend(declared_lints(&fs::read_to_string(file)?));
+            }
+        }
+        assert!(declared.contains(&"par_iter".to_string()));
+
+        for lint in &declared {
+            let phases = PHASES
+                .iter()
+                .filter(|phase| phase.lints.contains(&lint.as_str()))
+                .count();
+            let expected = usize::from(!LEFT_OUT.contains(&lint.as_str()));
+            assert_eq!(phases, expected, "`{lint}` is in {phases} phases");
+        }
+        for lint in PHASES.iter().flat_map(|phase| phase.lints) {
+            assert!(
+                declared.iter().any(|declared| declared == lint),
+                "`{lint}` isn't registered"
+            );
+        }
+        Ok(())
+    }
+
+    fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                rust_files(&path, files)?;
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of the lints declared by `declare_lint!` and dylint's macros, which
+    /// are given as `pub NAME,`.
+    fn declared_lints(source: &str) -> impl Iterator<Item = String> + '_ {
+        source.lines().filter_map(|line| {
+            let name = line.trim().strip_prefix("pub ")?.strip_suffix(',')?;
+            name.chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+                .then(|| name.to_lowercase())
+        })
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The contents of the Rust sources of a workspace, to put back the files a batch of
+/// fixes broke.
+pub(crate) struct Snapshot {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl Snapshot {
+    pub(crate) fn take(root: &Path) -> io::Result<Self> {
+        let mut files = BTreeMap::new();
+        collect(root, &mut files)?;
+        Ok(Snapshot { files })
+    }
+
+    /// The files whose contents differ from the snapshot.
+    pub(crate) fn changed_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut changed = Vec::new();
+        for (path, contents) in &self.files {
+            if fs::read(path)? != *contents {
+                changed.push(path.clone());
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Writes back the contents `paths` had in the snapshot.
+    pub(crate) fn restore(&self, paths: &[PathBuf]) -> io::Result<()> {
+        for path in paths {
+            if let Some(contents) = self.files.get(path) {
+                fs::write(path, contents)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the `.rs` files under `dir`, leaving out build outputs and hidden directories
+/// such as `.git`.
+fn collect(dir: &Path, files: &mut BTreeMap<PathBuf, Vec<u8>>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if entry.file_type()?.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                collect(&path, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.insert(path.clone(), fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io;
+
+    use super::Snapshot;
+
+    #[test]
+    fn restores_the_changed_sources() -> io::Result<()> {
+        let root = env::temp_dir().join(format!("cargo-mate-snapshot-{}", std::process::id()));
+        for dir in ["src", "target", ".git"] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        let lib = root.join("src/lib.rs");
+        let main = root.join("src/main.rs");
+        fs::write(&lib, "fn lib() {}")?;
+        fs::write(&main, "fn main() {}")?;
+        fs::write(root.join("target/out.rs"), "fn out() {}")?;
+        fs::write(root.join(".git/hook.rs"), "fn hook() {}")?;
+        fs::write(root.join("notes.txt"), "notes")?;
+
+        let snapshot = Snapshot::take(&root)?;
+        fs::write(&lib, "fn lib() { todo!() }")?;
+        fs::write(root.join("target/out.rs"), "")?;
+        fs::write(root.join(".git/hook.rs"), "")?;
+        fs::write(root.join("notes.txt"), "")?;
+
+        let changed = snapshot.changed_files()?;
+        assert_eq!(changed, [lib.clone()]);
+        snapshot.restore(&changed)?;
+        assert_eq!(fs::read_to_string(&lib)?, "fn lib() {}");
+        assert_eq!(fs::read_to_string(&main)?, "fn main() {}");
+        assert!(snapshot.changed_files()?.is_empty());
+
+        fs::remove_dir_all(&root)
+    }
+}
@@ -9,16 +9,19 @@ dylint_linting::dylint_library!();
 extern crate rustc_errors;
 extern crate rustc_hir;
 extern crate rustc_lint;
+extern crate rustc_middle;
 extern crate rustc_session;
 extern crate rustc_span;
 
 mod simple;
+mod try_fold;
 
 #[allow(clippy::no_mangle_with_rust_abi)]
 #[cfg_attr(not(feature = "rlib"), no_mangle)]
 
 pub fn register_lints(_sess: &rustc_session::Session, lint_store: &mut rustc_lint::LintStore) {
     lint_store.register_late_pass(|_| Box::new(simple::FoldSimple));
+    lint_store.register_late_pass(|_| Box::new(try_fold::FoldTry));
 }
 
 #[test]
@@ -1,6 +1,7 @@
 use rustc_errors::Applicability;
 use rustc_hir::{BinOpKind, Expr, ExprKind};
 use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_middle::ty::Ty;
 use rustc_session::{declare_lint, declare_lint_pass};
 use rustc_span::Symbol;
 use utils::span_to_snippet_macro;
@@ -32,6 +33,31 @@ enum MonoidType {
     Add,
 }
 
+/// Returns the identity of the monoid formed by `op` over `ty`, if we know it.
+pub(crate) fn identity_snippet(op: BinOpKind, ty: Ty<'_>) -> Option<&'static str> {
+    // Is the operator additive or multiplicative.
+    // This effects the choice of identity.
+    let mon_ty = match op {
+        BinOpKind::Add | BinOpKind::Sub | BinOpKind::BitXor | BinOpKind::BitOr => MonoidType::Add,
+        BinOpKind::Mul | BinOpKind::BitAnd => MonoidType::Mul,
+        _ => return None,
+    };
+
+    if ty.is_integral() {
+        match mon_ty {
+            MonoidType::Add => Some("0"),
+            MonoidType::Mul => Some("1"),
+        }
+    } else if ty.is_bool() {
+        match mon_ty {
+            MonoidType::Add => Some("false"),
+            MonoidType::Mul => Some("true"),
+        }
+    } else {
+        None
+    }
+}
+
 impl<'tcx> LateLintPass<'tcx> for FoldSimple {
     fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'_>) {
         // TODO: extract to helper function
@@ -65,29 +91,9 @@ impl<'tcx> LateLintPass<'tcx> for FoldSimple {
                 return;
             };
 
-            // Is the operator additive or multiplicative.
-            // This effects the choice of identity.
-            let mon_ty = match op.node {
-                BinOpKind::Add | BinOpKind::Sub | BinOpKind::BitXor | BinOpKind::BitOr => {
-                    MonoidType::Add
-                }
-                BinOpKind::Mul | BinOpKind::BitAnd => MonoidType::Mul,
-                _ => return,
-            };
-
             // Type check the accumulated parameter and assign the correct identity.
             let lhs_ty = cx.tcx.typeck(lhs.hir_id.owner.def_id).node_type(lhs.hir_id);
-            let id_snip = if lhs_ty.is_integral() {
-                match mon_ty {
-                    MonoidType::Add => "0",
-                    MonoidType::Mul => "1",
-                }
-            } else if lhs_ty.is_bool() {
-                match mon_ty {
-                    MonoidType::Add => "false",
-                    MonoidType::Mul => "true",
-                }
-            } else {
+            let Some(id_snip) = identity_snippet(op.node, lhs_ty) else {
                 return;
             };
 
@@ -102,7 +108,13 @@ impl<'tcx> LateLintPass<'tcx> for FoldSimple {
             let rhs_snip = span_to_snippet_macro(src_map, rhs.span);
             let op_snip = span_to_snippet_macro(src_map, op.span);
             let lhs_snip = span_to_snippet_macro(src_map, lhs.span);
-            let suggestion = format!("{lhs_snip} {op_snip} {recv_snip}.map(|{pat_snip}| {{{local_defs_snip} {rhs_snip}}}).fold({id_snip}, |mut {lhs_snip}, v| {{ {lhs_snip} {op_snip} v; {lhs_snip} }})");
+            // The terms of a subtraction are summed first, then subtracted once.
+            let fold_op_snip = if op.node == BinOpKind::Sub {
+                "+="
+            } else {
+                op_snip.as_str()
+            };
+            let suggestion = format!("{lhs_snip} {op_snip} {recv_snip}.map(|{pat_snip}| {{{local_defs_snip} {rhs_snip}}}).fold({id_snip}, |mut {lhs_snip}, v| {{ {lhs_snip} {fold_op_snip} v; {lhs_snip} }})");
 
             cx.span_lint(FOLD_SIMPLE, expr.span, |diag| {
                 diag.primary_message("implicit fold");
@@ -0,0 +1,221 @@
+use clippy_utils::visitors::{for_each_expr, is_local_used, Descend};
+use clippy_utils::{get_parent_expr, is_res_lang_ctor, path_to_local};
+use core::ops::ControlFlow;
+use rustc_errors::Applicability;
+use rustc_hir::{BinOpKind, Block, Expr, ExprKind, LangItem, MatchSource, Stmt, StmtKind};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_session::{declare_lint, declare_lint_pass};
+use rustc_span::Symbol;
+use utils::span_to_snippet_macro;
+
+use crate::simple::identity_snippet;
+
+declare_lint! {
+    /// ### What it does
+    /// Turns a fallible accumulation inside `try_for_each` into an explicit `try_fold`.
+    ///
+    /// ### Why is this bad?
+    /// The accumulator is captured by mutable reference, which blocks any parallel
+    /// reduction of the iterator.
+    ///
+    /// ### Known problems
+    /// The error type of a `Result` accumulation is not always inferable from the
+    /// rewritten closure, so those suggestions are not machine applicable.
+    ///
+    /// ### Example
+    /// ```rust
+    /// (values.iter()).try_for_each(|x| {
+    ///     total += x.checked_mul(2)?;
+    ///     return Some(());
+    /// })?;
+    /// ```
+    /// Use instead:
+    /// ```rust
+    /// total += (values.iter()).map(|x| { Some(x.checked_mul(2)?) }).try_fold(0, |mut total, v| { total += v?; Some(total) })?;
+    /// ```
+    pub FOLD_TRY,
+    Warn,
+    "suggest using explicit try_fold"
+}
+declare_lint_pass!(FoldTry => [FOLD_TRY]);
+
+impl<'tcx> LateLintPass<'tcx> for FoldTry {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'_>) {
+        let hir_map = cx.tcx.hir();
+        if let ExprKind::MethodCall(seg, recv, args, _span) = &expr.kind
+            && seg.ident.name == Symbol::intern("try_for_each")
+        {
+            assert_eq!(args.len(), 1);
+
+            // The rewrite yields the accumulated value where `C(())` was, which only a `?`
+            // can take in its stead.
+            if !is_tried(cx, expr) {
+                return;
+            }
+
+            let ExprKind::Closure(cls) = &args[0].kind else {
+                return;
+            };
+            let cls_body = hir_map.body(cls.body);
+            let ExprKind::Block(block, _) = cls_body.value.kind else {
+                return;
+            };
+
+            // Expected shape, as produced by `to_iter`:
+            // |pat| { pre_stmts; lhs op= rhs; return C(()); }
+            let Some((ctor, stmts)) = split_success_return(cx, block) else {
+                return;
+            };
+            let [pre_stmts @ .., acc_stmt] = stmts else {
+                return;
+            };
+            let (StmtKind::Semi(acc_expr) | StmtKind::Expr(acc_expr)) = acc_stmt.kind else {
+                return;
+            };
+            let ExprKind::AssignOp(op, lhs, rhs) = acc_expr.kind else {
+                return;
+            };
+
+            // The accumulator must be a captured local which is only touched by the update.
+            let Some(acc_id) = path_to_local(lhs) else {
+                return;
+            };
+            if is_local_used(cx, rhs, acc_id)
+                || pre_stmts.iter().any(|s| is_local_used(cx, s, acc_id))
+            {
+                return;
+            }
+
+            // An early success return would skip the item, which `map` can't express.
+            if pre_stmts
+                .iter()
+                .any(|s| has_success_return(cx, s) || matches!(s.kind, StmtKind::Item(_)))
+                || has_success_return_expr(cx, rhs)
+            {
+                return;
+            }
+
+            let lhs_ty = cx.tcx.typeck(lhs.hir_id.owner.def_id).node_type(lhs.hir_id);
+            let Some(id_snip) = identity_snippet(op.node, lhs_ty) else {
+                return;
+            };
+
+            let src_map = cx.sess().source_map();
+            let recv_snip = span_to_snippet_macro(src_map, recv.span);
+            let pre_snip = match pre_stmts {
+                [] => String::new(),
+                [fst, .., lst] => span_to_snippet_macro(src_map, fst.span.to(lst.span)),
+                [only] => span_to_snippet_macro(src_map, only.span),
+            };
+            let pat_snip = if cls_body.params.is_empty() {
+                String::new()
+            } else {
+                let pat_span = cls_body.params[0]
+                    .span
+                    .to(cls_body.params[cls_body.params.len() - 1].span);
+                span_to_snippet_macro(src_map, pat_span)
+            };
+            let rhs_snip = span_to_snippet_macro(src_map, rhs.span);
+            let op_snip = span_to_snippet_macro(src_map, op.span);
+            let lhs_snip = span_to_snippet_macro(src_map, lhs.span);
+            // The terms of a subtraction are summed first, then subtracted once.
+            let fold_op_snip = if op.node == BinOpKind::Sub {
+                "+="
+            } else {
+                op_snip.as_str()
+            };
+            let suggestion = format!("{lhs_snip} {op_snip} {recv_snip}.map(|{pat_snip}| {{ {pre_snip} {ctor}({rhs_snip}) }}).try_fold({id_snip}, |mut {lhs_snip}, v| {{ {lhs_snip} {fold_op_snip} v?; {ctor}({lhs_snip}) }})");
+
+            // With `Result` the error type of the mapped closure may need an annotation.
+            let applicability = if ctor == "Some" {
+                Applicability::MachineApplicable
+            } else {
+                Applicability::MaybeIncorrect
+            };
+
+            cx.span_lint(FOLD_TRY, expr.span, |diag| {
+                diag.primary_message("implicit fallible fold");
+                diag.span_suggestion(
+                    expr.span,
+                    "try using `try_fold` instead",
+                    suggestion,
+                    applicability,
+                );
+            });
+        }
+    }
+}
+
+/// Checks whether `expr` is the operand of a `?`.
+fn is_tried<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> bool {
+    get_parent_expr(cx, expr).is_some_and(|branch| {
+        matches!(branch.kind, ExprKind::Call(_, [arg]) if arg.hir_id == expr.hir_id)
+            && get_parent_expr(cx, branch).is_some_and(|parent| {
+                matches!(
+                    parent.kind,
+                    ExprKind::Match(_, _, MatchSource::TryDesugar(_))
+                )
+            })
+    })
+}
+
+/// Splits off the trailing `return Some(())`/`return Ok(())` of a `try_for_each` body,
+/// returning the constructor used and the remaining statements.
+fn split_success_return<'tcx>(
+    cx: &LateContext<'tcx>,
+    block: &'tcx Block<'tcx>,
+) -> Option<(&'static str, &'tcx [Stmt<'tcx>])> {
+    match (block.stmts, block.expr) {
+        (stmts, Some(e)) => success_ctor(cx, e).map(|c| (c, stmts)),
+        ([stmts @ .., last], None) => match last.kind {
+            StmtKind::Semi(e) | StmtKind::Expr(e) => success_ctor(cx, e).map(|c| (c, stmts)),
+            _ => None,
+        },
+        ([], None) => None,
+    }
+}
+
+/// Matches `C(())` or `return C(())` where `C` is `Some` or `Ok`.
+fn success_ctor(cx: &LateContext<'_>, expr: &Expr<'_>) -> Option<&'static str> {
+    let expr = match expr.kind {
+        ExprKind::Ret(Some(e)) => e,
+        _ => expr,
+    };
+    if let ExprKind::Call(ctor, [arg]) = expr.kind
+        && let ExprKind::Tup([]) = arg.kind
+        && let ExprKind::Path(qpath) = &ctor.kind
+    {
+        let res = cx.qpath_res(qpath, ctor.hir_id);
+        if is_res_lang_ctor(cx, res, LangItem::OptionSome) {
+            return Some("Some");
+        }
+        if is_res_lang_ctor(cx, res, LangItem::ResultOk) {
+            return Some("Ok");
+        }
+    }
+    None
+}
+
+fn has_success_return<'tcx>(cx: &LateContext<'tcx>, stmt: &'tcx Stmt<'tcx>) -> bool {
+    match stmt.kind {
+        StmtKind::Let(local) => local.init.map_or(false, |e| has_success_return_expr(cx, e)),
+        StmtKind::Semi(e) | StmtKind::Expr(e) => has_success_return_expr(cx, e),
+        StmtKind::Item(_) => false,
+    }
+}
+
+fn has_success_return_expr<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> bool {
+    for_each_expr(cx, expr, |e| {
+        if let ExprKind::Ret(Some(_)) = e.kind
+            && success_ctor(cx, e).is_some()
+        {
+            return ControlFlow::Break(());
+        }
+        // Returns inside nested closures belong to those closures.
+        if let ExprKind::Closure(_) = e.kind {
+            return ControlFlow::Continue(Descend::No);
+        }
+        ControlFlow::Continue(Descend::Yes)
+    })
+    .is_some()
+}
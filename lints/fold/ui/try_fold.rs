@@ -0,0 +1,50 @@
+// run-rustfix
+fn main() {
+    checked_total(&[1, 2, 3]);
+    early_skip(&[0, 1, 2]);
+    checked_balance(&[1, 2, 3]);
+    ignored(&[1, 2, 3]);
+}
+
+fn checked_total(values: &[u32]) -> Option<u32> {
+    let mut total = 0;
+    (values.iter()).try_for_each(|x| {
+        let doubled = x.checked_mul(2)?;
+        total += doubled.checked_add(1)?;
+        return Some(());
+    })?;
+    Some(total)
+}
+
+// no: skipping an item can't be expressed with `map`
+fn early_skip(values: &[u32]) -> Option<u32> {
+    let mut total = 0;
+    (values.iter()).try_for_each(|x| {
+        if *x == 0 {
+            return Some(());
+        }
+        total += x.checked_mul(2)?;
+        return Some(());
+    })?;
+    Some(total)
+}
+
+// should sum the terms in the fold, and subtract the sum once
+fn checked_balance(values: &[i32]) -> Option<i32> {
+    let mut balance = 100;
+    (values.iter()).try_for_each(|x| {
+        balance -= x.checked_mul(2)?;
+        return Some(());
+    })?;
+    Some(balance)
+}
+
+// no: the result isn't propagated with `?`
+fn ignored(values: &[u32]) -> u32 {
+    let mut total = 0;
+    (values.iter()).try_for_each(|x| {
+        total += x.checked_mul(2)?;
+        return Some(());
+    });
+    total
+}
@@ -0,0 +1,200 @@
+use clippy_utils::path_to_local_id;
+use clippy_utils::ty::is_copy;
+use rustc_ast::LitKind;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::{self as hir, BinOpKind, Expr, ExprKind, PatKind, StmtKind};
+use rustc_lint::LateContext;
+use rustc_middle::ty::UpvarCapture;
+use rustc_span::sym;
+use utils::variable_check::{check_trait_impl, check_variables, is_type_valid};
+
+/// Checks that the identity and operator of a fold can be handed to rayon.
+///
+/// rayon calls the identity once per split and shares the operator between
+/// threads, so the identity has to be cheap and free of side effects, and the
+/// operator must not mutate or share non thread safe captures.
+/// On failure the reason is returned so it can be reported to the user.
+pub(crate) fn check_fold_closures<'tcx>(
+    cx: &LateContext<'tcx>,
+    id_expr: &'tcx Expr<'tcx>,
+    op_expr: &'tcx Expr<'tcx>,
+) -> Result<(), String> {
+    let typeck = cx.typeck_results();
+
+    check_identity(cx, id_expr)?;
+    if !is_type_valid(cx, typeck.expr_ty(id_expr)) {
+        return Err("the accumulator can't be sent between threads".to_string());
+    }
+
+    if let ExprKind::Closure(closure) = op_expr.kind {
+        // The operator is shared between threads, and so are the values it captures by
+        // reference. Those it captures by value are sent along with it.
+        let shareable = typeck
+            .closure_min_captures_flattened(closure.def_id)
+            .all(|captured| {
+                let ty = captured.place.ty();
+                check_trait_impl(cx, ty, sym::Sync)
+                    && (!matches!(captured.info.capture_kind, UpvarCapture::ByValue)
+                        || check_trait_impl(cx, ty, sym::Send))
+            });
+        if !shareable {
+            return Err(
+                "the operator captures values that can't be shared between threads".to_string(),
+            );
+        }
+
+        let mut params = hir::HirIdSet::default();
+        let body = cx.tcx.hir().body(closure.body);
+        for param in body.params {
+            if let hir::PatKind::Binding(_, hir_id, _, _) = param.pat.kind {
+                params.insert(hir_id);
+            }
+        }
+        // The operator owns its arguments, so those may be mutated freely.
+        if !check_variables(cx, closure.def_id, body, &params, true) {
+            return Err("the operator mutates or captures state outside of the fold".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// An identity is accepted if it is built out of literals, constants, `Copy` locals
+/// which can be shared, and argument-less constructors such as `Vec::new()`. rayon
+/// calls it from a closure which may run several times, so it can't move a local.
+fn check_identity<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> Result<(), String> {
+    match expr.kind {
+        ExprKind::Lit(_) => Ok(()),
+        ExprKind::Unary(_, e) | ExprKind::Cast(e, _) | ExprKind::DropTemps(e) => {
+            check_identity(cx, e)
+        }
+        ExprKind::Binary(_, l, r) => {
+            check_identity(cx, l)?;
+            check_identity(cx, r)
+        }
+        ExprKind::Tup(es) | ExprKind::Array(es) => {
+            es.iter().try_for_each(|e| check_identity(cx, e))
+        }
+        ExprKind::Struct(_, fields, base) => {
+            fields.iter().try_for_each(|f| check_identity(cx, f.expr))?;
+            base.map_or(Ok(()), |b| check_identity(cx, b))
+        }
+        ExprKind::Path(ref qpath) => match cx.qpath_res(qpath, expr.hir_id) {
+            Res::Def(DefKind::Const | DefKind::AssocConst | DefKind::Ctor(..), _) => Ok(()),
+            Res::Local(_) => {
+                let ty = cx.typeck_results().expr_ty(expr);
+                if !is_copy(cx, ty) {
+                    return Err(format!(
+                        "the identity `{}` isn't `Copy`, so it can't be given to each split",
+                        snippet(cx, expr)
+                    ));
+                }
+                if !is_type_valid(cx, ty) {
+                    return Err(format!(
+                        "the identity `{}` can't be shared between threads",
+                        snippet(cx, expr)
+                    ));
+                }
+                Ok(())
+            }
+            _ => Err(format!(
+                "the identity `{}` can't be shared between threads",
+                snippet(cx, expr)
+            )),
+        },
+        ExprKind::Call(func, args) => {
+            let ExprKind::Path(ref qpath) = func.kind else {
+                return Err(not_pure(cx, expr));
+            };
+            let Some(def_id) = cx.qpath_res(qpath, func.hir_id).opt_def_id() else {
+                return Err(not_pure(cx, expr));
+            };
+            let name = cx.tcx.item_name(def_id);
+            let is_ctor = matches!(cx.tcx.def_kind(def_id), DefKind::Ctor(..));
+            let is_trivial_constructor =
+                args.is_empty() && matches!(name.as_str(), "new" | "default");
+            if !is_ctor && !is_trivial_constructor {
+                return Err(format!(
+                    "the identity calls `{name}`, which may allocate or have side effects \
+                     and would run once per split"
+                ));
+            }
+            args.iter().try_for_each(|e| check_identity(cx, e))
+        }
+        ExprKind::MethodCall(..) => Err(format!(
+            "the identity `{}` calls a method, which may have side effects and would run once \
+             per split",
+            snippet(cx, expr)
+        )),
+        ExprKind::Block(block, _) if block.stmts.is_empty() => {
+            block.expr.map_or(Ok(()), |e| check_identity(cx, e))
+        }
+        _ => Err(not_pure(cx, expr)),
+    }
+}
+
+/// The operator of a fold `|acc, v| acc op v` or `|mut acc, v| { acc op= v; acc }`.
+pub(crate) fn fold_operator<'tcx>(
+    cx: &LateContext<'tcx>,
+    op_expr: &'tcx Expr<'tcx>,
+) -> Option<BinOpKind> {
+    let ExprKind::Closure(closure) = op_expr.kind else {
+        return None;
+    };
+    let body = cx.tcx.hir().body(closure.body);
+    let [acc_param, item_param] = body.params else {
+        return None;
+    };
+    let (PatKind::Binding(_, acc_id, _, None), PatKind::Binding(_, item_id, _, None)) =
+        (acc_param.pat.kind, item_param.pat.kind)
+    else {
+        return None;
+    };
+
+    let (op, lhs, rhs) = match body.value.kind {
+        ExprKind::Block(block, _) => {
+            let ([stmt], Some(result)) = (block.stmts, block.expr) else {
+                return None;
+            };
+            let (StmtKind::Semi(update) | StmtKind::Expr(update)) = stmt.kind else {
+                return None;
+            };
+            let ExprKind::AssignOp(op, lhs, rhs) = update.kind else {
+                return None;
+            };
+            if !path_to_local_id(result, acc_id) {
+                return None;
+            }
+            (op.node, lhs, rhs)
+        }
+        ExprKind::Binary(op, lhs, rhs) => (op.node, lhs, rhs),
+        _ => return None,
+    };
+    (path_to_local_id(lhs, acc_id) && path_to_local_id(rhs, item_id)).then_some(op)
+}
+
+/// Checks whether `op` is associative with the literal `id_expr` as its identity, so
+/// that starting each split from it gives the sequential result.
+pub(crate) fn is_neutral_identity(op: BinOpKind, id_expr: &Expr<'_>) -> bool {
+    let ExprKind::Lit(lit) = id_expr.kind else {
+        return false;
+    };
+    match (op, lit.node) {
+        (BinOpKind::Add | BinOpKind::BitOr | BinOpKind::BitXor, LitKind::Int(n, _)) => n.get() == 0,
+        (BinOpKind::Mul, LitKind::Int(n, _)) => n.get() == 1,
+        (BinOpKind::BitOr | BinOpKind::BitXor, LitKind::Bool(b)) => !b,
+        (BinOpKind::BitAnd, LitKind::Bool(b)) => b,
+        _ => false,
+    }
+}
+
+fn not_pure(cx: &LateContext<'_>, expr: &Expr<'_>) -> String {
+    format!(
+        "the identity `{}` is not known to be cheap and pure, but would run once per split",
+        snippet(cx, expr)
+    )
+}
+
+fn snippet(cx: &LateContext<'_>, expr: &Expr<'_>) -> String {
+    utils::span_to_snippet_macro(cx.tcx.sess.source_map(), expr.span)
+}
@@ -4,6 +4,7 @@
 #[cfg(not(feature = "rlib"))]
 dylint_linting::dylint_library!();
 
+extern crate rustc_ast;
 #[cfg(feature = "rlib")]
 extern crate rustc_driver;
 extern crate rustc_errors;
@@ -13,12 +14,21 @@ extern crate rustc_middle;
 extern crate rustc_session;
 extern crate rustc_span;
 
+mod closure_check;
+mod par_counter;
+mod par_fold_combine;
 mod par_fold_simple;
+mod par_fold_try;
+mod par_reduction;
 
 #[allow(clippy::no_mangle_with_rust_abi)]
 #[cfg_attr(not(feature = "rlib"), no_mangle)]
 pub fn register_lints(_sess: &rustc_session::Session, lint_store: &mut rustc_lint::LintStore) {
     lint_store.register_late_pass(|_| Box::new(par_fold_simple::ParFoldSimple));
+    lint_store.register_late_pass(|_| Box::new(par_fold_combine::ParFoldCombine));
+    lint_store.register_late_pass(|_| Box::new(par_fold_try::ParTryFold));
+    lint_store.register_late_pass(|_| Box::new(par_reduction::ParReduction::default()));
+    lint_store.register_late_pass(|_| Box::new(par_counter::ParCounter));
 }
 
 #[test]
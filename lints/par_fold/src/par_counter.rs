@@ -0,0 +1,449 @@
+use clippy_utils::ty::get_iterator_item_ty;
+use clippy_utils::visitors::for_each_expr;
+use clippy_utils::{
+    get_enclosing_loop_or_multi_call_closure, get_parent_expr, is_trait_method, path_to_local,
+    path_to_local_id,
+};
+use core::ops::ControlFlow;
+use rustc_errors::Applicability;
+use rustc_hir::intravisit::Visitor;
+use rustc_hir::{self as hir, BinOpKind, Expr, ExprKind, HirId, Node, StmtKind};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_middle::ty::{self, Ty, UpvarCapture};
+use rustc_session::{declare_lint, declare_lint_pass};
+use rustc_span::{sym, Span, Symbol};
+use utils::global_effects::global_effects;
+use utils::par_modules::{iter_method_traits, record_par_suggestion, ParUse};
+use utils::rayon_crate::{crate_has_rayon, skip_without_rayon};
+use utils::span_to_snippet_macro;
+use utils::validator::Validator;
+use utils::variable_check::{check_trait_impl, is_type_valid};
+
+use crate::par_fold_simple::IterRenaming;
+use crate::par_reduction::chain_source;
+
+declare_lint! {
+    /// ### What it does
+    /// Suggests replacing counters updated by a `for_each` closure with a parallel
+    /// reduction, or with an atomic counter when the closure does more than counting.
+    ///
+    /// ### Why is this bad?
+    /// A captured counter is mutated by every iteration, which keeps the loop
+    /// sequential.
+    ///
+    /// ### Known problems
+    /// `fetch_add` wraps around on overflow where `+=` panics in debug builds.
+    ///
+    /// ### Example
+    /// ```rust
+    /// let mut total = 0;
+    /// numbers.iter().for_each(|x| total += x);
+    /// ```
+    /// Use instead:
+    /// ```rust
+    /// let mut total = 0;
+    /// total += numbers.par_iter().map(|x| x).sum::<i32>();
+    /// ```
+    pub WARN_PAR_COUNTER,
+    Warn,
+    "suggest parallel reductions or atomics for counters updated in `for_each`"
+}
+
+declare_lint_pass!(ParCounter => [WARN_PAR_COUNTER]);
+
+/// A `counter += value` in the closure, on an integer captured from the enclosing
+/// function.
+struct CounterUpdate<'tcx> {
+    counter: HirId,
+    update: &'tcx Expr<'tcx>,
+    value: &'tcx Expr<'tcx>,
+}
+
+impl<'tcx> LateLintPass<'tcx> for ParCounter {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(path, recv, [closure_arg], _span) = expr.kind
+            && path.ident.as_str() == "for_each"
+            && is_trait_method(cx, expr, sym::Iterator)
+            && let ExprKind::Closure(closure) = closure_arg.kind
+            && let Node::Stmt(stmt) = cx.tcx.parent_hir_node(expr.hir_id)
+            && let Some(source) = chain_source(cx, recv)
+            && let ExprKind::MethodCall(source_path, ..) = source.kind
+        {
+            // Items get moved between threads.
+            let typeck = cx.typeck_results();
+            let Some(item_ty) = get_iterator_item_ty(cx, typeck.expr_ty(recv)) else {
+                return;
+            };
+            if !check_trait_impl(cx, item_ty, sym::Send) {
+                return;
+            }
+
+            let body = cx.tcx.hir().body(closure.body);
+            let updates = counter_updates(cx, closure_arg.span, body.value);
+            if updates.is_empty() || !only_updated(cx, body.value, &updates) {
+                return;
+            }
+
+            // Apart from the counters, captured state must be shared immutably.
+            for captured in typeck.closure_min_captures_flattened(closure.def_id) {
+                if updates
+                    .iter()
+                    .any(|u| u.counter == captured.get_root_variable())
+                {
+                    continue;
+                }
+                if matches!(
+                    captured.info.capture_kind,
+                    UpvarCapture::ByRef(
+                        ty::BorrowKind::MutBorrow | ty::BorrowKind::UniqueImmBorrow
+                    )
+                ) || !is_type_valid(cx, captured.place.ty())
+                {
+                    return;
+                }
+            }
+            if !global_effects(cx, closure.def_id).is_empty() {
+                return;
+            }
+
+            // The rest of the chain runs in parallel too.
+            let is_mut = ["iter_mut", "chunks_mut"].contains(&source_path.ident.as_str());
+            let mut validator = Validator::new(cx, is_mut);
+            validator.visit_expr(recv);
+            if !validator.is_valid || validator.thread_local.is_some() {
+                return;
+            }
+
+            let mut ir = IterRenaming::new();
+            ir.traverse_iter_chain(cx, recv);
+            // Without rayon, there is nothing to suggest: the opportunity is only counted.
+            if ir.suggestions.is_empty() && crate_has_rayon(cx) {
+                return;
+            }
+
+            if let Some(reduction) = reduction(cx, body, &updates, item_ty) {
+                if skip_without_rayon(cx, WARN_PAR_COUNTER, expr.hir_id) {
+                    return;
+                }
+                ir.methods.extend(reduction.methods);
+                ir.traits.extend(iter_method_traits(cx, &ir.methods));
+                let par_use = ParUse {
+                    hir_id: expr.hir_id,
+                    traits: ir.traits,
+                };
+                record_par_suggestion(cx, WARN_PAR_COUNTER, expr.hir_id, &[par_use]);
+                let counter = span_to_snippet_macro(cx.sess().source_map(), reduction.lhs_span);
+                ir.suggestions
+                    .push((expr.span.shrink_to_lo(), format!("{counter} += ")));
+                ir.suggestions
+                    .push((path.ident.span.with_hi(expr.span.hi()), reduction.tail));
+
+                cx.span_lint(WARN_PAR_COUNTER, expr.span, |diag| {
+                    diag.primary_message("counter updated sequentially in `for_each`");
+                    diag.multipart_suggestion_verbose(
+                        "try counting with a parallel reduction",
+                        ir.suggestions,
+                        Applicability::MachineApplicable,
+                    );
+                });
+            } else if let Some(atomics) = atomic_rewrite(cx, stmt, &updates) {
+                if skip_without_rayon(cx, WARN_PAR_COUNTER, expr.hir_id) {
+                    return;
+                }
+                ir.methods.push(path.ident.name);
+                ir.traits.extend(iter_method_traits(cx, &ir.methods));
+                let par_use = ParUse {
+                    hir_id: expr.hir_id,
+                    traits: ir.traits,
+                };
+                record_par_suggestion(cx, WARN_PAR_COUNTER, expr.hir_id, &[par_use]);
+                ir.suggestions.extend(atomics);
+
+                cx.span_lint(WARN_PAR_COUNTER, expr.span, |diag| {
+                    diag.primary_message("counter updated sequentially in `for_each`");
+                    diag.note(
+                        "`fetch_add` wraps around on overflow, where `+=` panics in debug builds",
+                    );
+                    diag.multipart_suggestion_verbose(
+                        "try using an atomic counter and a parallel iterator",
+                        ir.suggestions,
+                        Applicability::MaybeIncorrect,
+                    );
+                });
+            }
+        }
+    }
+}
+
+/// Finds the `+=` on integers declared outside of the closure spanning `closure_span`.
+fn counter_updates<'tcx>(
+    cx: &LateContext<'tcx>,
+    closure_span: Span,
+    body: &'tcx Expr<'tcx>,
+) -> Vec<CounterUpdate<'tcx>> {
+    let mut updates = Vec::new();
+    for_each_expr(cx, body, |e| {
+        if let ExprKind::AssignOp(op, lhs, value) = e.kind
+            && op.node == BinOpKind::Add
+            && let Some(counter) = path_to_local(lhs)
+            && !closure_span.contains(cx.tcx.hir().span(counter))
+            && cx.typeck_results().expr_ty(lhs).is_integral()
+        {
+            updates.push(CounterUpdate {
+                counter,
+                update: e,
+                value,
+            });
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    updates
+}
+
+/// Checks that the counters aren't used in the closure besides being incremented.
+fn only_updated<'tcx>(
+    cx: &LateContext<'tcx>,
+    body: &'tcx Expr<'tcx>,
+    updates: &[CounterUpdate<'tcx>],
+) -> bool {
+    for_each_expr(cx, body, |e| {
+        let is_counter = updates.iter().any(|u| path_to_local_id(e, u.counter));
+        let is_update_lhs = updates.iter().any(
+            |u| matches!(u.update.kind, ExprKind::AssignOp(_, lhs, _) if lhs.hir_id == e.hir_id),
+        );
+        if is_counter && !is_update_lhs {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .is_none()
+}
+
+struct Reduction {
+    lhs_span: Span,
+    /// Replaces `for_each(..)`.
+    tail: String,
+    /// The methods of the parallel iterator `tail` calls.
+    methods: Vec<Symbol>,
+}
+
+/// Builds the reduction replacing a closure whose only effect is a single counter
+/// update, possibly guarded by an `if`: `count += 1` becomes `count()`, `total += x`
+/// becomes `map(|x| x).sum()`, and the guard a `filter`.
+fn reduction<'tcx>(
+    cx: &LateContext<'tcx>,
+    body: &'tcx hir::Body<'tcx>,
+    updates: &[CounterUpdate<'tcx>],
+    item_ty: Ty<'tcx>,
+) -> Option<Reduction> {
+    let [update] = updates else {
+        return None;
+    };
+    let [param] = body.params else {
+        return None;
+    };
+
+    let (cond, expr) = match peel_blocks(body.value).kind {
+        ExprKind::If(cond, then, None) => (Some(cond), peel_blocks(then)),
+        _ => (None, peel_blocks(body.value)),
+    };
+    let ExprKind::AssignOp(_, lhs, _) = expr.kind else {
+        return None;
+    };
+    if expr.hir_id != update.update.hir_id {
+        return None;
+    }
+
+    let typeck = cx.typeck_results();
+    let counter_ty = typeck.expr_ty(lhs);
+    let value_ty = typeck.expr_ty(update.value);
+    if value_ty != counter_ty
+        && !matches!(value_ty.kind(), ty::Ref(_, inner, _) if *inner == counter_ty)
+    {
+        return None;
+    }
+
+    let param_snip = span_to_snippet_macro(cx.sess().source_map(), param.pat.span);
+    let mut tail = String::new();
+    let mut methods = Vec::new();
+    if let Some(cond) = cond {
+        // The filter sees a reference to the item, which the pattern copies out of.
+        if matches!(cond.kind, ExprKind::Let(..)) || !check_trait_impl(cx, item_ty, sym::Copy) {
+            return None;
+        }
+        let cond_snip = span_to_snippet_macro(cx.sess().source_map(), cond.span);
+        tail.push_str(&format!("filter(|&{param_snip}| {cond_snip})."));
+        methods.push(Symbol::intern("filter"));
+    }
+
+    let is_one = matches!(
+        update.value.kind,
+        ExprKind::Lit(lit) if matches!(lit.node, rustc_ast::LitKind::Int(n, _) if n.get() == 1)
+    );
+    if is_one && counter_ty.is_usize() {
+        tail.push_str("count()");
+        methods.push(Symbol::intern("count"));
+    } else if is_one {
+        tail.push_str(&format!("map(|_| 1).sum::<{counter_ty}>()"));
+        methods.extend([Symbol::intern("map"), Symbol::intern("sum")]);
+    } else {
+        let value_snip = span_to_snippet_macro(cx.sess().source_map(), update.value.span);
+        tail.push_str(&format!(
+            "map(|{param_snip}| {value_snip}).sum::<{counter_ty}>()"
+        ));
+        methods.extend([Symbol::intern("map"), Symbol::intern("sum")]);
+    }
+
+    Some(Reduction {
+        lhs_span: lhs.span,
+        tail,
+        methods,
+    })
+}
+
+/// Builds the edits turning each counter into an atomic: its declaration, its updates
+/// in the closure and its reads after the `for_each` statement `stmt`.
+fn atomic_rewrite<'tcx>(
+    cx: &LateContext<'tcx>,
+    stmt: &'tcx hir::Stmt<'tcx>,
+    updates: &[CounterUpdate<'tcx>],
+) -> Option<Vec<(Span, String)>> {
+    let body = cx.tcx.hir().body(cx.enclosing_body?);
+    let mut suggestions = Vec::new();
+    let mut counters: Vec<HirId> = Vec::new();
+    for update in updates {
+        if !counters.contains(&update.counter) {
+            counters.push(update.counter);
+        }
+    }
+
+    for &counter in &counters {
+        let counter_ty = cx.typeck_results().node_type(counter);
+        let atomic = atomic_type(counter_ty)?;
+        let Node::Pat(pat) = cx.tcx.hir_node(counter) else {
+            return None;
+        };
+        let hir::PatKind::Binding(_, _, ident, None) = pat.kind else {
+            return None;
+        };
+        let Node::LetStmt(local) = cx.tcx.parent_hir_node(counter) else {
+            return None;
+        };
+        let init = local.init?;
+        let init_snip = span_to_snippet_macro(cx.sess().source_map(), init.span);
+        suggestions.push((
+            pat.span.to(init.span),
+            format!("{ident} = std::sync::atomic::{atomic}::new({init_snip})"),
+        ));
+
+        for update in updates.iter().filter(|u| u.counter == counter) {
+            let value_ty = cx.typeck_results().expr_ty(update.value);
+            let value_snip = span_to_snippet_macro(cx.sess().source_map(), update.value.span);
+            let value = if value_ty == counter_ty {
+                value_snip
+            } else if let ExprKind::Path(_) = update.value.kind {
+                format!("*{value_snip}")
+            } else {
+                return None;
+            };
+            let fetch_add =
+                format!("{ident}.fetch_add({value}, std::sync::atomic::Ordering::Relaxed)");
+            // `fetch_add` returns the previous value, which a closure or block must not.
+            let is_statement = matches!(
+                cx.tcx.parent_hir_node(update.update.hir_id),
+                Node::Stmt(hir::Stmt {
+                    kind: StmtKind::Semi(_),
+                    ..
+                })
+            );
+            let sugg = if is_statement {
+                fetch_add
+            } else {
+                format!("{{ {fetch_add}; }}")
+            };
+            suggestions.push((update.update.span, sugg));
+        }
+
+        let reads = counter_reads(cx, body.value, stmt, counter)?;
+        let single_read = reads.len() == 1;
+        for read in reads {
+            let sugg =
+                if single_read && get_enclosing_loop_or_multi_call_closure(cx, read).is_none() {
+                    format!("{ident}.into_inner()")
+                } else {
+                    format!("{ident}.load(std::sync::atomic::Ordering::Relaxed)")
+                };
+            suggestions.push((read.span, sugg));
+        }
+    }
+    Some(suggestions)
+}
+
+/// Finds the uses of `counter` outside of the `for_each` statement `stmt`, which must
+/// all come after it and only read the counter.
+fn counter_reads<'tcx>(
+    cx: &LateContext<'tcx>,
+    body: &'tcx Expr<'tcx>,
+    stmt: &'tcx hir::Stmt<'tcx>,
+    counter: HirId,
+) -> Option<Vec<&'tcx Expr<'tcx>>> {
+    let mut reads = Vec::new();
+    let found_other_use = for_each_expr(cx, body, |e| {
+        if !path_to_local_id(e, counter) || stmt.span.contains(e.span) {
+            return ControlFlow::Continue(());
+        }
+        let parent = get_parent_expr(cx, e);
+        let is_read = e.span.lo() > stmt.span.hi()
+            && !e.span.from_expansion()
+            && parent.map_or(true, |p| {
+                !p.span.from_expansion()
+                    && match p.kind {
+                        ExprKind::Assign(lhs, _, _) | ExprKind::AssignOp(_, lhs, _) => {
+                            lhs.hir_id != e.hir_id
+                        }
+                        ExprKind::AddrOf(_, hir::Mutability::Mut, _) => false,
+                        ExprKind::MethodCall(_, recv, _, _) => recv.hir_id != e.hir_id,
+                        _ => true,
+                    }
+            });
+        if is_read {
+            reads.push(e);
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    });
+    found_other_use.is_none().then_some(reads)
+}
+
+/// The atomic counterpart of an integer type, e.g. `AtomicUsize` for `usize`.
+fn atomic_type(ty: Ty<'_>) -> Option<String> {
+    if !matches!(ty.kind(), ty::Int(_) | ty::Uint(_)) || ty.to_string().ends_with("128") {
+        return None;
+    }
+    let name = ty.to_string();
+    let mut chars = name.chars();
+    let first = chars.next()?.to_ascii_uppercase();
+    Some(format!("Atomic{first}{}", chars.as_str()))
+}
+
+/// Peels blocks made of a single expression or statement, but not `unsafe` ones.
+fn peel_blocks<'tcx>(expr: &'tcx Expr<'tcx>) -> &'tcx Expr<'tcx> {
+    if let ExprKind::Block(block, _) = expr.kind
+        && block.rules == hir::BlockCheckMode::DefaultBlock
+    {
+        match (block.stmts, block.expr) {
+            ([], Some(inner)) => return peel_blocks(inner),
+            (
+                [hir::Stmt {
+                    kind: StmtKind::Semi(inner) | StmtKind::Expr(inner),
+                    ..
+                }],
+                None,
+            ) => return peel_blocks(inner),
+            _ => {}
+        }
+    }
+    expr
+}
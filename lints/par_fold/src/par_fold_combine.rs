@@ -0,0 +1,248 @@
+use clippy_utils::ty::is_type_diagnostic_item;
+use clippy_utils::visitors::for_each_expr;
+use clippy_utils::{get_parent_expr, path_to_local_id};
+use core::ops::ControlFlow;
+use rustc_errors::Applicability;
+use rustc_hir::{Body, Expr, ExprKind, HirId, PatKind};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_middle::ty::{Ty, TyKind};
+use rustc_session::{declare_lint, declare_lint_pass};
+use rustc_span::{sym, Symbol};
+use utils::par_modules::{iter_method_traits, record_par_suggestion, ParUse};
+use utils::rayon_crate::{crate_has_rayon, skip_without_rayon};
+use utils::span_to_snippet_macro;
+
+use crate::closure_check::check_fold_closures;
+use crate::par_fold_simple::IterRenaming;
+
+declare_lint! {
+    pub WARN_PAR_FOLD_COMBINE,
+    Warn,
+    "suggest using parallel fold with a combining reduce"
+}
+
+declare_lint_pass!(ParFoldCombine => [WARN_PAR_FOLD_COMBINE]);
+
+/// How two partial accumulators of a given type are merged together.
+#[derive(Clone, Copy)]
+enum Combine {
+    /// `a.append(&mut b)`, for `Vec`, `VecDeque` and `LinkedList`.
+    Append,
+    /// `a.push_str(&b)`, for `String`.
+    PushStr,
+    /// `a.extend(b)`, for sets and maps.
+    Extend,
+}
+
+impl Combine {
+    fn of(cx: &LateContext<'_>, ty: Ty<'_>) -> Option<Self> {
+        if [sym::Vec, sym::VecDeque, sym::LinkedList]
+            .into_iter()
+            .any(|s| is_type_diagnostic_item(cx, ty, s))
+        {
+            Some(Combine::Append)
+        } else if is_type_diagnostic_item(cx, ty, sym::String) {
+            Some(Combine::PushStr)
+        } else if [sym::HashMap, sym::HashSet, sym::BTreeMap, sym::BTreeSet]
+            .into_iter()
+            .any(|s| is_type_diagnostic_item(cx, ty, s))
+        {
+            Some(Combine::Extend)
+        } else {
+            None
+        }
+    }
+
+    /// Methods which only add elements at the back of the accumulator, so that
+    /// merging the partial results in order gives back the sequential result.
+    fn growing_methods(self) -> &'static [&'static str] {
+        match self {
+            Combine::Append => &["push", "push_back", "extend", "append"],
+            Combine::PushStr => &["push", "push_str", "extend"],
+            Combine::Extend => &["insert", "extend"],
+        }
+    }
+
+    fn snippet(self, a: &str, b: &str) -> String {
+        match self {
+            Combine::Append => format!("{a}.append(&mut {b});"),
+            Combine::PushStr => format!("{a}.push_str(&{b});"),
+            Combine::Extend => format!("{a}.extend({b});"),
+        }
+    }
+}
+
+impl<'tcx> LateLintPass<'tcx> for ParFoldCombine {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(path, recv, args, _span) = &expr.kind
+            && path.ident.name == Symbol::intern("fold")
+        {
+            assert_eq!(args.len(), 2);
+            let id_expr = &args[0];
+            let op_expr = &args[1];
+
+            let ExprKind::Closure(op_cls) = op_expr.kind else {
+                return;
+            };
+            let type_chk = cx.tcx.typeck(expr.hir_id.owner.def_id);
+            let op_ty = type_chk.node_type(op_expr.hir_id);
+            let TyKind::Closure(_, io_tys) = op_ty.kind() else {
+                return;
+            };
+
+            let op_cls_arg_tys = io_tys
+                .as_closure()
+                .sig()
+                .input(0)
+                .skip_binder()
+                .tuple_fields();
+            assert_eq!(op_cls_arg_tys.len(), 2);
+            // Reducible folds are handled by `ParFoldSimple`.
+            if op_cls_arg_tys[0] == op_cls_arg_tys[1] {
+                return;
+            }
+            let acc_ty = op_cls_arg_tys[0];
+
+            // Derive a combine operation for the accumulator, either directly or
+            // field-wise for tuples.
+            let combines = if let TyKind::Tuple(tys) = acc_ty.kind() {
+                let Some(combines) = tys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| Combine::of(cx, ty).map(|c| (Some(i), c)))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return;
+                };
+                combines
+            } else if let Some(c) = Combine::of(cx, acc_ty) {
+                vec![(None, c)]
+            } else {
+                return;
+            };
+
+            let op_body = cx.tcx.hir().body(op_cls.body);
+            let PatKind::Binding(_, acc_id, _, None) = op_body.params[0].pat.kind else {
+                return;
+            };
+            if !only_grows(cx, op_body, acc_id, &combines) {
+                return;
+            }
+
+            if let Err(reason) = check_fold_closures(cx, id_expr, op_expr) {
+                if !crate_has_rayon(cx) {
+                    return;
+                }
+                cx.span_lint(WARN_PAR_FOLD_COMBINE, expr.span, |diag| {
+                    diag.primary_message("sequential fold can't be parallelized");
+                    diag.note(reason);
+                });
+                return;
+            }
+
+            let mut ir = IterRenaming::new();
+            ir.traverse_iter_chain(cx, recv);
+
+            let src_map = cx.sess().source_map();
+            let id_snip = span_to_snippet_macro(src_map, id_expr.span);
+
+            let combine_snip = combines
+                .iter()
+                .map(|(field, c)| match field {
+                    Some(i) => c.snippet(&format!("a.{i}"), &format!("b.{i}")),
+                    None => c.snippet("a", "b"),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let b_mut = if combines.iter().any(|(_, c)| matches!(c, Combine::Append)) {
+                "mut "
+            } else {
+                ""
+            };
+
+            let suggestion = format!("|| {id_snip}");
+            let suggestion2 =
+                format!(".reduce(|| {id_snip}, |mut a, {b_mut}b| {{ {combine_snip} a }})");
+            ir.suggestions.extend_from_slice(&[
+                (id_expr.span, suggestion),
+                (expr.span.shrink_to_hi(), suggestion2),
+            ]);
+
+            if skip_without_rayon(cx, WARN_PAR_FOLD_COMBINE, expr.hir_id) {
+                return;
+            }
+            ir.methods
+                .extend([Symbol::intern("fold"), Symbol::intern("reduce")]);
+            ir.traits.extend(iter_method_traits(cx, &ir.methods));
+            let par_use = ParUse {
+                hir_id: expr.hir_id,
+                traits: ir.traits,
+            };
+            record_par_suggestion(cx, WARN_PAR_FOLD_COMBINE, expr.hir_id, &[par_use]);
+
+            cx.span_lint(WARN_PAR_FOLD_COMBINE, expr.span, |diag| {
+                diag.primary_message("sequential fold");
+                diag.multipart_suggestion(
+                    "try using a parallel fold and combining the partial results",
+                    ir.suggestions,
+                    Applicability::MachineApplicable,
+                );
+            });
+        }
+    }
+}
+
+/// Checks that the fold operator only ever grows the accumulator and then returns it.
+fn only_grows<'tcx>(
+    cx: &LateContext<'tcx>,
+    body: &'tcx Body<'tcx>,
+    acc_id: HirId,
+    combines: &[(Option<usize>, Combine)],
+) -> bool {
+    let tail = match body.value.kind {
+        ExprKind::Block(block, _) => block.expr,
+        _ => Some(body.value),
+    };
+    let Some(tail) = tail else {
+        return false;
+    };
+    if !path_to_local_id(tail, acc_id) {
+        return false;
+    }
+
+    for_each_expr(cx, body.value, |e| {
+        if !path_to_local_id(e, acc_id) || e.hir_id == tail.hir_id {
+            return ControlFlow::Continue(());
+        }
+
+        // Find which part of the accumulator is being used.
+        let (target, combine) = if let Some(parent) = get_parent_expr(cx, e)
+            && let ExprKind::Field(_, ident) = parent.kind
+        {
+            let Ok(i) = ident.as_str().parse::<usize>() else {
+                return ControlFlow::Break(());
+            };
+            let Some((_, c)) = combines.iter().find(|(f, _)| *f == Some(i)) else {
+                return ControlFlow::Break(());
+            };
+            (parent, *c)
+        } else if let [(None, c)] = combines {
+            (e, *c)
+        } else {
+            return ControlFlow::Break(());
+        };
+
+        match get_parent_expr(cx, target) {
+            Some(Expr {
+                kind: ExprKind::MethodCall(seg, recv, _, _),
+                ..
+            }) if recv.hir_id == target.hir_id
+                && combine.growing_methods().contains(&seg.ident.as_str()) =>
+            {
+                ControlFlow::Continue(())
+            }
+            _ => ControlFlow::Break(()),
+        }
+    })
+    .is_none()
+}
@@ -1,11 +1,17 @@
 use rustc_errors::Applicability;
+use rustc_hir::def_id::DefId;
 use rustc_hir::{Expr, ExprKind, HirId};
 use rustc_lint::{LateContext, LateLintPass, LintContext};
 use rustc_middle::ty::TyKind;
 use rustc_session::{declare_lint, declare_lint_pass};
 use rustc_span::{Span, Symbol};
+use utils::par_modules::{iter_method_traits, record_par_suggestion, ParUse};
+use utils::par_sources::par_source;
+use utils::rayon_crate::{crate_has_rayon, skip_without_rayon};
 use utils::span_to_snippet_macro;
 
+use crate::closure_check::{check_fold_closures, fold_operator, is_neutral_identity};
+
 declare_lint! {
     pub WARN_PAR_FOLD_SIMPLE,
     Warn,
@@ -14,13 +20,13 @@ declare_lint! {
 
 declare_lint_pass!(ParFoldSimple => [WARN_PAR_FOLD_SIMPLE]);
 impl<'tcx> LateLintPass<'tcx> for ParFoldSimple {
-    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'_>) {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
         if let ExprKind::MethodCall(path, recv, args, _span) = &expr.kind
             && path.ident.name == Symbol::intern("fold")
         {
             assert_eq!(args.len(), 2);
-            let id_expr = args[0];
-            let op_expr = args[1];
+            let id_expr = &args[0];
+            let op_expr = &args[1];
 
             // Are we in the reduce case?
             // i.e. closure arg types are equal
@@ -45,8 +51,19 @@ impl<'tcx> LateLintPass<'tcx> for ParFoldSimple {
                 return;
             }
 
+            if let Err(reason) = check_fold_closures(cx, id_expr, op_expr) {
+                if !crate_has_rayon(cx) {
+                    return;
+                }
+                cx.span_lint(WARN_PAR_FOLD_SIMPLE, expr.span, |diag| {
+                    diag.primary_message("sequential fold can't be parallelized");
+                    diag.note(reason);
+                });
+                return;
+            }
+
             let mut ir = IterRenaming::new();
-            ir.traverse_iter_chain(recv);
+            ir.traverse_iter_chain(cx, recv);
 
             let src_map = cx.sess().source_map();
             let id_snip = span_to_snippet_macro(src_map, id_expr.span);
@@ -56,12 +73,38 @@ impl<'tcx> LateLintPass<'tcx> for ParFoldSimple {
             ir.suggestions
                 .extend_from_slice(&[(path.ident.span, suggestion), (id_expr.span, suggestion2)]);
 
+            if skip_without_rayon(cx, WARN_PAR_FOLD_SIMPLE, expr.hir_id) {
+                return;
+            }
+            ir.methods.push(Symbol::intern("reduce"));
+            ir.traits.extend(iter_method_traits(cx, &ir.methods));
+            let par_use = ParUse {
+                hir_id: expr.hir_id,
+                traits: ir.traits,
+            };
+            record_par_suggestion(cx, WARN_PAR_FOLD_SIMPLE, expr.hir_id, &[par_use]);
+
+            // Every split starts from the identity, which only gives the sequential result
+            // if it is the neutral element of the operator.
+            let is_neutral =
+                fold_operator(cx, op_expr).is_some_and(|op| is_neutral_identity(op, id_expr));
+            let applicability = if is_neutral {
+                Applicability::MachineApplicable
+            } else {
+                Applicability::MaybeIncorrect
+            };
             cx.span_lint(WARN_PAR_FOLD_SIMPLE, expr.span, |diag| {
                 diag.primary_message("sequential fold");
+                if !is_neutral {
+                    diag.note(format!(
+                        "every split starts from `{id_snip}`, which is only right if it is the \
+                         identity of the operator"
+                    ));
+                }
                 diag.multipart_suggestion(
                     "try using a parallel fold on the iterator",
                     ir.suggestions,
-                    Applicability::MachineApplicable,
+                    applicability,
                 );
             });
         }
@@ -70,41 +113,45 @@ impl<'tcx> LateLintPass<'tcx> for ParFoldSimple {
 
 // Traverse an iterator chain and rename all occurrences
 // of sequential iterator calls to parallel ones.
-struct IterRenaming {
-    suggestions: Vec<(Span, String)>,
+pub(crate) struct IterRenaming {
+    pub(crate) suggestions: Vec<(Span, String)>,
+    /// The rayon traits of the renamed sources.
+    pub(crate) traits: Vec<DefId>,
+    /// The other methods of the chain, which become methods of the parallel iterator.
+    pub(crate) methods: Vec<Symbol>,
     seen: Vec<HirId>,
 }
 
 impl IterRenaming {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         IterRenaming {
             suggestions: vec![],
+            traits: vec![],
+            methods: vec![],
             seen: vec![],
         }
     }
 
-    fn traverse_iter_chain(&mut self, expr: &Expr) {
+    pub(crate) fn traverse_iter_chain<'tcx>(
+        &mut self,
+        cx: &LateContext<'tcx>,
+        expr: &'tcx Expr<'tcx>,
+    ) {
         if self.seen.contains(&expr.hir_id) {
             return;
         }
         self.seen.push(expr.hir_id);
 
         if let ExprKind::MethodCall(path, recv, args, _span) = &expr.kind {
-            // TODO: Optimize this.
-            let seq_names = vec![
-                Symbol::intern("iter"),
-                Symbol::intern("iter_mut"),
-                Symbol::intern("into_iter"),
-            ];
-            let par_names = vec!["par_iter", "par_iter_mut", "into_par_iter"];
-            for (sm, pm) in seq_names.into_iter().zip(par_names.into_iter()) {
-                if path.ident.name == sm {
-                    self.suggestions.push((path.ident.span, pm.to_string()));
-                    break;
-                }
+            if let Some(call) = par_source(cx, expr) {
+                self.suggestions
+                    .push((path.ident.span, call.source.par.to_string()));
+                self.traits.push(call.trait_def_id);
+            } else {
+                self.methods.push(path.ident.name);
             }
-            self.traverse_iter_chain(recv);
-            args.iter().for_each(|e| self.traverse_iter_chain(e));
+            self.traverse_iter_chain(cx, recv);
+            args.iter().for_each(|e| self.traverse_iter_chain(cx, e));
         }
     }
 }
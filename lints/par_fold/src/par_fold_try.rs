@@ -0,0 +1,204 @@
+use clippy_utils::ty::is_type_diagnostic_item;
+use clippy_utils::{is_res_lang_ctor, path_to_local_id};
+use rustc_errors::Applicability;
+use rustc_hir::{BinOpKind, Expr, ExprKind, HirId, LangItem, MatchSource, PatKind, StmtKind};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_middle::ty::TyKind;
+use rustc_session::{declare_lint, declare_lint_pass};
+use rustc_span::{sym, Symbol};
+use utils::par_modules::{iter_method_traits, record_par_suggestion, ParUse};
+use utils::rayon_crate::{crate_has_rayon, skip_without_rayon};
+use utils::span_to_snippet_macro;
+
+use crate::closure_check::{check_fold_closures, is_neutral_identity};
+use crate::par_fold_simple::IterRenaming;
+
+declare_lint! {
+    pub WARN_PAR_TRY_FOLD,
+    Warn,
+    "suggest using parallel try_fold"
+}
+
+declare_lint_pass!(ParTryFold => [WARN_PAR_TRY_FOLD]);
+impl<'tcx> LateLintPass<'tcx> for ParTryFold {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(path, recv, args, _span) = &expr.kind
+            && path.ident.name == Symbol::intern("try_fold")
+        {
+            assert_eq!(args.len(), 2);
+            let id_expr = &args[0];
+            let op_expr = &args[1];
+
+            let ExprKind::Closure(_op_cls) = op_expr.kind else {
+                return;
+            };
+            let type_chk = cx.tcx.typeck(expr.hir_id.owner.def_id);
+            let op_ty = type_chk.node_type(op_expr.hir_id);
+            let TyKind::Closure(_, io_tys) = op_ty.kind() else {
+                return;
+            };
+
+            // We are in the reduce case when the closure has the shape
+            // `(T, R) -> R` with `R` either `Option<T>` or `Result<T, E>`,
+            // i.e. the items are fallible accumulators themselves.
+            let sig = io_tys.as_closure().sig().skip_binder();
+            let op_cls_arg_tys = sig.inputs()[0].tuple_fields();
+            assert_eq!(op_cls_arg_tys.len(), 2);
+            let (acc_ty, item_ty) = (op_cls_arg_tys[0], op_cls_arg_tys[1]);
+            if item_ty != sig.output() {
+                return;
+            }
+            let ctor = if is_type_diagnostic_item(cx, item_ty, sym::Option) {
+                "Some"
+            } else if is_type_diagnostic_item(cx, item_ty, sym::Result) {
+                "Ok"
+            } else {
+                return;
+            };
+            let TyKind::Adt(_, item_args) = item_ty.kind() else {
+                return;
+            };
+            if item_args.type_at(0) != acc_ty {
+                return;
+            }
+
+            if let Err(reason) = check_fold_closures(cx, id_expr, op_expr) {
+                if !crate_has_rayon(cx) {
+                    return;
+                }
+                cx.span_lint(WARN_PAR_TRY_FOLD, expr.span, |diag| {
+                    diag.primary_message("sequential try_fold can't be parallelized");
+                    diag.note(reason);
+                });
+                return;
+            }
+
+            let mut ir = IterRenaming::new();
+            ir.traverse_iter_chain(cx, recv);
+
+            let src_map = cx.sess().source_map();
+            let id_snip = span_to_snippet_macro(src_map, id_expr.span);
+            let op_snip = span_to_snippet_macro(src_map, op_expr.span);
+
+            // rayon's `try_fold` yields one partial result per split, so we combine
+            // them by feeding the right hand side back through the fold operator.
+            let suggestion = format!("|| {id_snip}");
+            let suggestion2 =
+                format!(".try_reduce(|| {id_snip}, |a, b| ({op_snip})(a, {ctor}(b)))");
+            ir.suggestions.extend_from_slice(&[
+                (id_expr.span, suggestion),
+                (expr.span.shrink_to_hi(), suggestion2),
+            ]);
+
+            // Feeding a partial result back through the operator is only exact for the
+            // steps `FOLD_TRY` generates. The error type of `Ok(b)` is not always inferable.
+            let is_step = is_accumulation_step(cx, id_expr, op_expr);
+            let applicability = if is_step && ctor == "Some" {
+                Applicability::MachineApplicable
+            } else {
+                Applicability::MaybeIncorrect
+            };
+
+            if skip_without_rayon(cx, WARN_PAR_TRY_FOLD, expr.hir_id) {
+                return;
+            }
+            ir.methods
+                .extend([Symbol::intern("try_fold"), Symbol::intern("try_reduce")]);
+            ir.traits.extend(iter_method_traits(cx, &ir.methods));
+            let par_use = ParUse {
+                hir_id: expr.hir_id,
+                traits: ir.traits,
+            };
+            record_par_suggestion(cx, WARN_PAR_TRY_FOLD, expr.hir_id, &[par_use]);
+
+            cx.span_lint(WARN_PAR_TRY_FOLD, expr.span, |diag| {
+                diag.primary_message("sequential try_fold");
+                if !is_step {
+                    diag.note(format!(
+                        "the partial results are combined through the operator, which is only \
+                         right if it is associative with `{id_snip}` as its identity"
+                    ));
+                }
+                diag.multipart_suggestion(
+                    "try using a parallel try_fold on the iterator",
+                    ir.suggestions,
+                    applicability,
+                );
+            });
+        }
+    }
+}
+
+/// Checks whether the operator is an accumulation step `|acc, v| C(acc op v?)` or
+/// `|mut acc, v| { acc op= v?; C(acc) }`, as `FOLD_TRY` generates, with an associative
+/// `op` whose identity is the initial value.
+fn is_accumulation_step<'tcx>(
+    cx: &LateContext<'tcx>,
+    id_expr: &'tcx Expr<'tcx>,
+    op_expr: &'tcx Expr<'tcx>,
+) -> bool {
+    let ExprKind::Closure(closure) = op_expr.kind else {
+        return false;
+    };
+    let body = cx.tcx.hir().body(closure.body);
+    let [acc_param, item_param] = body.params else {
+        return false;
+    };
+    let (PatKind::Binding(_, acc_id, _, None), PatKind::Binding(_, item_id, _, None)) =
+        (acc_param.pat.kind, item_param.pat.kind)
+    else {
+        return false;
+    };
+
+    let (op, lhs, rhs) = match body.value.kind {
+        ExprKind::Block(block, _) => {
+            let ([stmt], Some(result)) = (block.stmts, block.expr) else {
+                return false;
+            };
+            let (StmtKind::Semi(update) | StmtKind::Expr(update)) = stmt.kind else {
+                return false;
+            };
+            let ExprKind::AssignOp(op, lhs, rhs) = update.kind else {
+                return false;
+            };
+            if !success(cx, result).is_some_and(|acc| path_to_local_id(acc, acc_id)) {
+                return false;
+            }
+            (op.node, lhs, rhs)
+        }
+        _ => {
+            let Some(value) = success(cx, body.value) else {
+                return false;
+            };
+            let ExprKind::Binary(op, lhs, rhs) = value.kind else {
+                return false;
+            };
+            (op.node, lhs, rhs)
+        }
+    };
+    path_to_local_id(lhs, acc_id) && is_tried(rhs, item_id) && is_neutral_identity(op, id_expr)
+}
+
+/// The value wrapped by `Some(value)` or `Ok(value)`.
+fn success<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> Option<&'tcx Expr<'tcx>> {
+    if let ExprKind::Call(ctor, [value]) = expr.kind
+        && let ExprKind::Path(ref qpath) = ctor.kind
+    {
+        let res = cx.qpath_res(qpath, ctor.hir_id);
+        if is_res_lang_ctor(cx, res, LangItem::OptionSome)
+            || is_res_lang_ctor(cx, res, LangItem::ResultOk)
+        {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Checks whether `expr` is `item?`.
+fn is_tried(expr: &Expr<'_>, item_id: HirId) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Match(scrutinee, _, MatchSource::TryDesugar(_))
+            if matches!(scrutinee.kind, ExprKind::Call(_, [arg]) if path_to_local_id(arg, item_id))
+    )
+}
@@ -0,0 +1,247 @@
+use clippy_utils::is_trait_method;
+use clippy_utils::ty::get_iterator_item_ty;
+use rustc_errors::Applicability;
+use rustc_hir::intravisit::Visitor;
+use rustc_hir::{Expr, ExprKind, HirId};
+use rustc_lint::{LateContext, LateLintPass};
+use rustc_middle::ty::Ty;
+use rustc_session::{declare_lint, impl_lint_pass};
+use rustc_span::{sym, Span};
+use utils::par_chains::is_par_chain_reported;
+use utils::par_modules::{iter_method_traits, record_par_suggestion, ParUse};
+use utils::par_sources::{par_source, std_source};
+use utils::rayon_crate::{crate_has_rayon, skip_without_rayon};
+use utils::validator::Validator;
+use utils::variable_check::check_trait_impl;
+
+use crate::par_fold_simple::IterRenaming;
+
+declare_lint! {
+    /// ### What it does
+    /// Suggests running terminal reductions such as `sum`, `count` or `max_by_key`
+    /// on a parallel iterator.
+    ///
+    /// ### Why is this bad?
+    /// Reductions over large iterators are easy to split between threads.
+    ///
+    /// ### Known problems
+    /// Floating point sums and products may round differently in parallel.
+    ///
+    /// Chains which `par_iter` reports as a whole are left to it.
+    ///
+    /// ### Example
+    /// ```rust
+    /// let total: i32 = numbers.iter().map(|x| x * 2).sum();
+    /// ```
+    /// Use instead:
+    /// ```rust
+    /// let total: i32 = numbers.par_iter().map(|x| x * 2).sum();
+    /// ```
+    pub WARN_PAR_REDUCTION,
+    Warn,
+    "suggest using parallel reductions"
+}
+
+#[derive(Default)]
+pub struct ParReduction {
+    /// Reported at the end of the crate, once `par_iter` has reported its chains.
+    reductions: Vec<Reduction>,
+}
+
+impl_lint_pass!(ParReduction => [WARN_PAR_REDUCTION]);
+
+/// A reduction which can run in parallel.
+struct Reduction {
+    hir_id: HirId,
+    span: Span,
+    /// The source of its chain.
+    source: HirId,
+    notes: Vec<String>,
+    suggestions: Vec<(Span, String)>,
+    applicability: Applicability,
+    par_use: ParUse,
+}
+
+/// Which of several equal items a reduction returns.
+#[derive(Clone, Copy)]
+enum Ties {
+    Irrelevant,
+    First,
+    Last,
+}
+
+/// Terminal reductions together with their tie-breaking. rayon provides a method of the
+/// same name for each of them, which reduces adjacent items in order and so keeps the
+/// tie-breaking of `std`.
+const REDUCTIONS: &[(&str, Ties)] = &[
+    ("sum", Ties::Irrelevant),
+    ("product", Ties::Irrelevant),
+    ("count", Ties::Irrelevant),
+    ("min", Ties::First),
+    ("min_by", Ties::First),
+    ("min_by_key", Ties::First),
+    ("max", Ties::Last),
+    ("max_by", Ties::Last),
+    ("max_by_key", Ties::Last),
+];
+
+/// Adapters which exist with the same meaning on rayon's `ParallelIterator`, and take
+/// the same arguments. rayon's `flat_map` needs parallel iterators out of its closure.
+const ADAPTERS: &[&str] = &["map", "filter", "filter_map", "cloned", "copied", "inspect"];
+
+impl<'tcx> LateLintPass<'tcx> for ParReduction {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(path, recv, args, _span) = expr.kind
+            && let Some(&(_, ties)) = REDUCTIONS
+                .iter()
+                .find(|(name, _)| path.ident.as_str() == *name)
+            && is_trait_method(cx, expr, sym::Iterator)
+        {
+            let Some(source) = chain_source(cx, recv) else {
+                return;
+            };
+
+            // Items get moved between threads.
+            let typeck = cx.typeck_results();
+            let Some(item_ty) = get_iterator_item_ty(cx, typeck.expr_ty(recv)) else {
+                return;
+            };
+            if !check_trait_impl(cx, item_ty, sym::Send) {
+                return;
+            }
+
+            // The closures of the adapters, comparators and key functions are shared
+            // between threads.
+            if args
+                .iter()
+                .any(|arg| !matches!(arg.kind, ExprKind::Closure(_)))
+            {
+                return;
+            }
+            let ExprKind::MethodCall(source_path, ..) = source.kind else {
+                return;
+            };
+            let is_mut = ["iter_mut", "chunks_mut"].contains(&source_path.ident.as_str());
+            let mut validator = Validator::new(cx, is_mut);
+            validator.visit_expr(expr);
+            if !validator.is_valid {
+                return;
+            }
+
+            let mut ir = IterRenaming::new();
+            ir.traverse_iter_chain(cx, recv);
+            // Without rayon, there is nothing to suggest: the opportunity is only counted.
+            if ir.suggestions.is_empty() && crate_has_rayon(cx) {
+                return;
+            }
+
+            let mut notes = Vec::new();
+            let mut applicability = Applicability::MachineApplicable;
+            // `count` doesn't look at the items, so only sums and products can round.
+            if matches!(path.ident.as_str(), "sum" | "product")
+                && item_ty.peel_refs().is_floating_point()
+            {
+                notes.push(
+                    "floating point arithmetic is not associative, so the parallel result may \
+                     round differently"
+                        .to_string(),
+                );
+                applicability = Applicability::MaybeIncorrect;
+            }
+            if !matches!(ties, Ties::Irrelevant) && !has_indistinguishable_ties(item_ty) {
+                let which = if matches!(ties, Ties::First) {
+                    "first"
+                } else {
+                    "last"
+                };
+                notes.push(format!(
+                    "like `std`, rayon's `{}` returns the {which} of several equal items only if \
+                     the comparison is a total order",
+                    path.ident
+                ));
+                applicability = Applicability::MaybeIncorrect;
+            }
+            // Each of the worker threads sees its own value of a thread local.
+            if let Some(name) = validator.thread_local {
+                notes.push(format!(
+                    "`{name}` is a thread local, which has a separate value on each thread"
+                ));
+                applicability = Applicability::MaybeIncorrect;
+            }
+
+            ir.methods.push(path.ident.name);
+            ir.traits.extend(iter_method_traits(cx, &ir.methods));
+            self.reductions.push(Reduction {
+                hir_id: expr.hir_id,
+                span: expr.span,
+                source: source.hir_id,
+                notes,
+                suggestions: ir.suggestions,
+                applicability,
+                par_use: ParUse {
+                    hir_id: expr.hir_id,
+                    traits: ir.traits,
+                },
+            });
+        }
+    }
+
+    fn check_crate_post(&mut self, cx: &LateContext<'tcx>) {
+        for reduction in core::mem::take(&mut self.reductions) {
+            if is_par_chain_reported(reduction.source)
+                || skip_without_rayon(cx, WARN_PAR_REDUCTION, reduction.hir_id)
+            {
+                continue;
+            }
+            record_par_suggestion(
+                cx,
+                WARN_PAR_REDUCTION,
+                reduction.hir_id,
+                &[reduction.par_use],
+            );
+
+            cx.tcx.node_span_lint(
+                WARN_PAR_REDUCTION,
+                reduction.hir_id,
+                reduction.span,
+                |diag| {
+                    diag.primary_message("sequential reduction");
+                    for note in reduction.notes {
+                        diag.note(note);
+                    }
+                    diag.multipart_suggestion_verbose(
+                        "try using a parallel reduction on the iterator",
+                        reduction.suggestions,
+                        reduction.applicability,
+                    );
+                },
+            );
+        }
+    }
+}
+
+/// The source of a chain whose adapters all exist on rayon's parallel iterators, if it
+/// can be turned into one. Without rayon, sources are recognized by their `std` types.
+pub(crate) fn chain_source<'tcx>(
+    cx: &LateContext<'tcx>,
+    mut expr: &'tcx Expr<'tcx>,
+) -> Option<&'tcx Expr<'tcx>> {
+    let has_rayon = crate_has_rayon(cx);
+    while let ExprKind::MethodCall(seg, recv, _, _) = expr.kind {
+        if par_source(cx, expr).is_some() || (!has_rayon && std_source(cx, expr)) {
+            return Some(expr);
+        }
+        if !ADAPTERS.contains(&seg.ident.as_str()) {
+            return None;
+        }
+        expr = recv;
+    }
+    None
+}
+
+/// Equal integers, booleans or chars can't be told apart, so the tie-breaking of
+/// `min`/`max` can't be observed.
+fn has_indistinguishable_ties(ty: Ty<'_>) -> bool {
+    let ty = ty.peel_refs();
+    ty.is_integral() || ty.is_bool() || ty.is_char()
+}
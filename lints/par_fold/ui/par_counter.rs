@@ -0,0 +1,76 @@
+// run-rustfix
+
+#[allow(unused_imports)]
+use rayon::prelude::*;
+
+fn main() {
+    warn_count();
+    warn_sum();
+    warn_filtered_count();
+    warn_atomic();
+    no_warn_other_mutation();
+    no_warn_mutating_chain();
+}
+
+fn warn_count() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let mut count: usize = 0;
+    numbers.iter().for_each(|_| count += 1);
+    println!("Count: {}", count);
+}
+
+fn warn_sum() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let mut total = 0;
+    numbers.iter().for_each(|x| total += x);
+    println!("Total: {}", total);
+}
+
+fn warn_filtered_count() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let mut evens: usize = 0;
+    numbers.iter().for_each(|x| {
+        if x % 2 == 0 {
+            evens += 1
+        }
+    });
+    println!("Evens: {}", evens);
+}
+
+fn warn_atomic() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let mut hits = 0;
+    numbers.iter().for_each(|x| {
+        hits += 1;
+        process(*x);
+    });
+    let result = hits;
+    println!("Hits: {}", result);
+}
+
+fn no_warn_other_mutation() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let mut count = 0;
+    let mut seen = Vec::new();
+    numbers.iter().for_each(|x| {
+        count += 1;
+        seen.push(*x);
+    });
+    println!("Count: {} {:?}", count, seen);
+}
+
+fn no_warn_mutating_chain() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let mut calls = 0;
+    let mut total = 0;
+    numbers
+        .iter()
+        .map(|x| {
+            calls += 1;
+            x
+        })
+        .for_each(|x| total += x);
+    println!("Total: {} {}", total, calls);
+}
+
+fn process(_x: i32) {}
@@ -0,0 +1,46 @@
+#![allow(unused_variables)]
+
+use std::cell::RefCell;
+
+fn main() {
+    big_identity();
+    side_effect_identity();
+    refcell_operator();
+    owned_identity();
+}
+
+// no: the identity allocates once per split
+fn big_identity() {
+    let xs = vec![1, 2, 3];
+    let v = xs.iter().fold(Vec::with_capacity(64), |mut v, x| {
+        v.push(*x);
+        v
+    });
+}
+
+// no: the identity has side effects
+fn side_effect_identity() {
+    let mut ids = 0..;
+    let xs = vec![1, 2, 3];
+    let sum = xs.iter().copied().fold(ids.next().unwrap(), |a, b| a + b);
+}
+
+// no: the operator shares a `RefCell`
+fn refcell_operator() {
+    let calls = RefCell::new(0);
+    let xs = vec![1, 2, 3];
+    let sum = xs.iter().copied().fold(0, |a, b| {
+        *calls.borrow_mut() += 1;
+        a + b
+    });
+}
+
+// no: the identity is moved into the fold, but every split needs its own
+fn owned_identity() {
+    let prefix = String::from("> ");
+    let words = vec!["a", "b"];
+    let line = words.iter().fold(prefix, |mut s, w| {
+        s.push_str(w);
+        s
+    });
+}
@@ -0,0 +1,63 @@
+// run-rustfix
+
+#[allow(unused_imports)]
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+fn main() {
+    collect_vec();
+    collect_set();
+    sorted_vec();
+    split_words();
+    collect_and_count();
+}
+
+fn collect_vec() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let doubled = numbers.iter().map(|x| x * 2).fold(Vec::new(), |mut v, x| {
+        v.push(x);
+        v
+    });
+    println!("{:?}", doubled);
+}
+
+fn collect_set() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let evens = numbers.iter().fold(HashSet::new(), |mut s, x| {
+        s.insert(x % 2);
+        s
+    });
+    println!("{:?}", evens);
+}
+
+// no: the operator reorders the accumulator
+fn sorted_vec() {
+    let numbers = vec![5, 4, 3, 2, 1];
+    let sorted = numbers.iter().fold(Vec::new(), |mut v, x| {
+        v.push(*x);
+        v.sort();
+        v
+    });
+    println!("{:?}", sorted);
+}
+
+fn split_words() {
+    let words = vec!["a", "bb", "ccc"];
+    let (lens, text) = words.iter().fold((Vec::new(), String::new()), |mut acc, w| {
+        acc.0.push(w.len());
+        acc.1.push_str(w);
+        acc
+    });
+    println!("{:?} {}", lens, text);
+}
+
+// no: the count has no combiner
+fn collect_and_count() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let (seen, count) = numbers.iter().fold((Vec::new(), 0), |mut acc, x| {
+        acc.0.push(*x);
+        acc.1 += 1;
+        acc
+    });
+    println!("{:?} {}", seen, count);
+}
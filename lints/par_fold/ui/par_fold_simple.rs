@@ -5,6 +5,7 @@ use rayon::prelude::*;
 
 fn main() {
     warn_fold_simple();
+    offset_fold();
 }
 
 fn warn_fold_simple() {
@@ -17,3 +18,12 @@ fn warn_fold_simple() {
 
     println!("Sum: {}", sum);
 }
+
+// maybe: the identity is added once per split
+fn offset_fold() {
+    let offset = 10;
+    let numbers = vec![1, 2, 3, 4, 5];
+    let sum = numbers.iter().copied().fold(offset, |a, b| a + b);
+
+    println!("Sum: {}", sum);
+}
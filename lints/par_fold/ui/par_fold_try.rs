@@ -0,0 +1,31 @@
+// run-rustfix
+
+#[allow(unused_imports)]
+use rayon::prelude::*;
+
+fn main() {
+    warn_par_try_fold();
+    warn_par_try_fold_capped();
+}
+
+fn warn_par_try_fold() -> Option<u32> {
+    let mut total = 0;
+    let numbers = vec![1u32, 2, 3, 4, 5];
+    total += numbers
+        .iter()
+        .map(|x| x.checked_mul(2))
+        .try_fold(0, |acc, v| Some(acc + v?))?;
+
+    println!("Total: {}", total);
+    Some(total)
+}
+
+// should parallelize, but not automatically: the operator doesn't just accumulate
+fn warn_par_try_fold_capped() -> Option<u32> {
+    let numbers = vec![1u32, 2, 3, 4, 5];
+    let total = numbers
+        .iter()
+        .map(|x| x.checked_mul(2))
+        .try_fold(0, |acc, v| if acc > 10 { None } else { Some(acc * 2 + v?) })?;
+    Some(total)
+}
@@ -0,0 +1,94 @@
+// run-rustfix
+
+#[allow(unused_imports)]
+use rayon::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn main() {
+    warn_sum();
+    warn_count();
+    warn_max_by_key();
+    warn_float_sum();
+    warn_float_count();
+    no_warn_not_send();
+    no_warn_flat_map();
+    no_warn_mutating_map();
+    no_warn_refcell_map();
+    no_warn_rc_map();
+}
+
+fn warn_sum() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let total: i32 = numbers.iter().map(|x| x * 2).sum();
+    println!("Total: {}", total);
+}
+
+fn warn_count() {
+    let numbers = vec![1, 2, 3, 4, 5];
+    let evens = numbers.iter().filter(|x| *x % 2 == 0).count();
+    println!("Evens: {}", evens);
+}
+
+fn warn_max_by_key() {
+    let names = vec!["Alice", "Bob", "Carol"];
+    let longest = names.iter().max_by_key(|name| name.len());
+    println!("Longest: {:?}", longest);
+}
+
+fn warn_float_sum() {
+    let values = vec![0.1, 0.2, 0.3];
+    let total: f64 = values.iter().sum();
+    println!("Total: {}", total);
+}
+
+fn warn_float_count() {
+    let values = vec![0.1, 0.2, 0.3];
+    let large = values.iter().filter(|x| **x > 0.15).count();
+    println!("Large: {}", large);
+}
+
+fn no_warn_not_send() {
+    let values = vec![Rc::new(1), Rc::new(2)];
+    let smallest = values.into_iter().min();
+    println!("Smallest: {:?}", smallest);
+}
+
+fn no_warn_flat_map() {
+    let rows = vec![vec![1, 2], vec![3]];
+    let total: i32 = rows.iter().flat_map(|row| row.iter()).sum();
+    println!("Total: {}", total);
+}
+
+fn no_warn_mutating_map() {
+    let numbers = vec![1, 2, 3];
+    let mut calls = 0;
+    let total: i32 = numbers
+        .iter()
+        .map(|x| {
+            calls += 1;
+            x
+        })
+        .sum();
+    println!("Total: {} in {} calls", total, calls);
+}
+
+fn no_warn_refcell_map() {
+    let numbers = vec![1, 2, 3];
+    let calls = RefCell::new(0);
+    let total: i32 = numbers
+        .iter()
+        .map(|x| {
+            *calls.borrow_mut() += 1;
+            x
+        })
+        .sum();
+    println!("Total: {} in {:?} calls", total, calls);
+}
+
+fn no_warn_rc_map() {
+    let numbers = vec![1, 2, 3];
+    let scale = Rc::new(2);
+    let total: i32 = numbers.iter().map(|x| x * *scale).sum();
+    println!("Total: {}", total);
+}
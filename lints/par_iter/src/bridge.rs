@@ -0,0 +1,67 @@
+use clippy_utils::ty::get_iterator_item_ty;
+use clippy_utils::{get_parent_expr, get_trait_def_id, is_trait_method};
+use rustc_hir as hir;
+use rustc_lint::LateContext;
+use rustc_span::sym;
+use utils::par_sources::{implements_rayon_trait, par_source_with, ParSource};
+use utils::variable_check::check_trait_impl;
+
+/// Checks whether `expr` starts a sequential iterator chain which rayon can only run
+/// with `par_bridge`: the iterator has no parallel counterpart, such as the iterators of
+/// channels, directories, readers or of the crate, but it and its items can be sent to
+/// the worker threads. The sources of `registered` have a parallel counterpart as well.
+pub(crate) fn is_bridge_source<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx hir::Expr<'tcx>,
+    registered: &'static [ParSource],
+) -> bool {
+    if expr.span.from_expansion() || par_source_with(cx, expr, registered).is_some() {
+        return false;
+    }
+    let Some(parent) = get_parent_expr(cx, expr) else {
+        return false;
+    };
+    if !matches!(parent.kind, hir::ExprKind::MethodCall(_, recv, _, _) if recv.hir_id == expr.hir_id)
+        || !is_trait_method(cx, parent, sym::Iterator)
+    {
+        return false;
+    }
+
+    let typeck = cx.typeck_results();
+    // The chain starts here, rather than at an iterator further down.
+    if let hir::ExprKind::MethodCall(_, recv, _, _) = expr.kind
+        && check_trait_impl(cx, typeck.expr_ty_adjusted(recv), sym::Iterator)
+    {
+        return false;
+    }
+
+    let iter_ty = typeck.expr_ty(expr);
+    // Ranges and the like are iterators with a parallel counterpart of their own.
+    if get_trait_def_id(cx.tcx, &["rayon", "iter", "IntoParallelIterator"])
+        .map_or(true, |trait_def_id| {
+            implements_rayon_trait(cx, iter_ty, trait_def_id)
+        })
+    {
+        return false;
+    }
+    check_trait_impl(cx, iter_ty, sym::Send)
+        && get_iterator_item_ty(cx, iter_ty)
+            .is_some_and(|item| check_trait_impl(cx, item, sym::Send))
+}
+
+/// The method calls of the chain from `top` down to `source`, whose values are the
+/// sequential iterators moved into the bridge.
+pub(crate) fn chain_calls<'tcx>(
+    mut top: &'tcx hir::Expr<'tcx>,
+    source: &'tcx hir::Expr<'tcx>,
+) -> hir::HirIdSet {
+    let mut calls = hir::HirIdSet::default();
+    calls.insert(source.hir_id);
+    while top.hir_id != source.hir_id
+        && let hir::ExprKind::MethodCall(_, recv, _, _) = top.kind
+    {
+        calls.insert(top.hir_id);
+        top = recv;
+    }
+    calls
+}
@@ -0,0 +1,269 @@
+use clippy_utils::get_trait_def_id;
+use rustc_hir::def_id::DefId;
+use rustc_infer::infer::{InferCtxt, TyCtxtInferExt};
+use rustc_infer::traits::{Obligation, ObligationCause};
+use rustc_lint::LateContext;
+use rustc_middle::ty::{self, GenericArgs, GenericArgsRef, ParamEnv, Ty, TyCtxt};
+use rustc_span::{Symbol, DUMMY_SP};
+use rustc_trait_selection::infer::InferCtxtExt;
+use rustc_trait_selection::traits::{NormalizeExt, ObligationCtxt};
+use utils::par_sources::{ParSource, SourceCall};
+
+use crate::multi_source::multi_source;
+
+/// How an adapter of the sequential chain carries over to the parallel one.
+#[derive(Clone, Copy)]
+pub(crate) enum Step {
+    /// The parallel iterator has the same method.
+    Same,
+    /// `flat_map` with a closure returning a sequential iterator, which rayon's
+    /// `flat_map` only takes if that iterator gets parallelized as well, and
+    /// `flat_map_iter` takes as it is.
+    FlatMapIter,
+}
+
+/// Follows the type of the parallel iterator through a chain of adapters, so that
+/// each adapter can be checked against the traits the type actually implements.
+/// For instance `rev` and `zip` need an `IndexedParallelIterator`, which the iterators
+/// of a `HashSet` or a `filter` are not.
+pub(crate) struct ParChain<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    infcx: InferCtxt<'tcx>,
+    par_iter_trait: DefId,
+    indexed_trait: DefId,
+    /// The parallel iterator produced so far, `None` once it can't be followed.
+    current: Option<Ty<'tcx>>,
+    /// The registered source whose declared semantics stand in for the type of the
+    /// iterator, when it is an iterator of another crate rather than one of rayon's.
+    declared: Option<&'static ParSource>,
+}
+
+impl<'tcx> ParChain<'tcx> {
+    /// Resolves the parallel iterator produced by the rayon counterpart of `call`,
+    /// e.g. `<Vec<i32> as IntoParallelIterator>::Iter` to `rayon::vec::IntoIter<i32>`.
+    pub(crate) fn new(cx: &LateContext<'tcx>, call: &SourceCall<'tcx>) -> Option<Self> {
+        let mut chain = Self::from_source(cx, call.trait_def_id, call.source.par, call.self_ty)?;
+        if call.source.indexed.is_some() && !chain.produces(false) {
+            chain.current = None;
+            chain.declared = Some(call.source);
+        }
+        Some(chain)
+    }
+
+    /// Resolves the parallel iterator produced by `par_bridge` on the sequential
+    /// iterator `iter_ty`, which is never indexed.
+    pub(crate) fn bridge(cx: &LateContext<'tcx>, iter_ty: Ty<'tcx>) -> Option<Self> {
+        let bridge_trait = get_trait_def_id(cx.tcx, &["rayon", "iter", "ParallelBridge"])?;
+        Self::from_source(cx, bridge_trait, "par_bridge", iter_ty)
+    }
+
+    /// Resolves the parallel iterator produced by `into_par_iter` on `ty`, for values
+    /// such as `&Vec<T>` which are given to rayon as they are.
+    pub(crate) fn into_par_iter(cx: &LateContext<'tcx>, ty: Ty<'tcx>) -> Option<Self> {
+        let into_trait = get_trait_def_id(cx.tcx, &["rayon", "iter", "IntoParallelIterator"])?;
+        Self::from_source(cx, into_trait, "into_par_iter", ty)
+    }
+
+    fn from_source(
+        cx: &LateContext<'tcx>,
+        trait_def_id: DefId,
+        name: &str,
+        self_ty: Ty<'tcx>,
+    ) -> Option<Self> {
+        let par_iter_trait = get_trait_def_id(cx.tcx, &["rayon", "iter", "ParallelIterator"])?;
+        let indexed_trait =
+            get_trait_def_id(cx.tcx, &["rayon", "iter", "IndexedParallelIterator"])?;
+        let mut chain = ParChain {
+            tcx: cx.tcx,
+            param_env: cx.param_env,
+            infcx: cx.tcx.infer_ctxt().build(),
+            par_iter_trait,
+            indexed_trait,
+            current: None,
+            declared: None,
+        };
+        let method = chain.method(trait_def_id, name)?;
+        chain.current = Some(chain.output(method, self_ty));
+        Some(chain)
+    }
+
+    /// Checks that the adapter `name` can be called on the current parallel iterator
+    /// with arguments of the types `arg_tys`, and moves on to the iterator it returns.
+    /// Methods which aren't rayon's, such as `Option::map_or` on the result of a chain,
+    /// are accepted and stop the tracking. Returns `None` if the adapter can't be called.
+    pub(crate) fn step(&mut self, name: &str, arg_tys: &[Ty<'tcx>]) -> Option<Step> {
+        if let Some(source) = self.declared {
+            return self.step_declared(source, name).then_some(Step::Same);
+        }
+        let Some(current) = self.current else {
+            return Some(Step::Same);
+        };
+        let Some((method, trait_def_id)) = [self.par_iter_trait, self.indexed_trait]
+            .into_iter()
+            .find_map(|t| self.method(t, name).map(|m| (m, t)))
+        else {
+            self.current = None;
+            return Some(Step::Same);
+        };
+        if !self
+            .infcx
+            .type_implements_trait(trait_def_id, [current], self.param_env)
+            .may_apply()
+        {
+            return None;
+        }
+        // The iterators combined by `zip` and the like are converted on their own.
+        if multi_source(name).is_some() {
+            self.current = Some(self.output(method, current));
+            return Some(Step::Same);
+        }
+        if let Some(output) = self.call(method, current, arg_tys) {
+            self.current = Some(output);
+            return Some(Step::Same);
+        }
+        // A closure returning a sequential iterator is given to `flat_map_iter` instead.
+        if name == "flat_map"
+            && let Some(flat_map_iter) = self.method(self.par_iter_trait, "flat_map_iter")
+            && let Some(output) = self.call(flat_map_iter, current, arg_tys)
+        {
+            self.current = Some(output);
+            return Some(Step::FlatMapIter);
+        }
+        None
+    }
+
+    /// The return type of `method` called on `self_ty` with arguments of the types
+    /// `arg_tys`, if they satisfy its bounds, e.g. if the closure given to `flat_map`
+    /// returns something rayon can iterate over in parallel.
+    fn call(&self, method: DefId, self_ty: Ty<'tcx>, arg_tys: &[Ty<'tcx>]) -> Option<Ty<'tcx>> {
+        self.infcx
+            .commit_if_ok(|_| {
+                let args = self.fresh_args(method, self_ty);
+                let sig = self.tcx.fn_sig(method).instantiate(self.tcx, args);
+                let sig = self.tcx.instantiate_bound_regions_with_erased(sig);
+                let [_, inputs @ ..] = sig.inputs() else {
+                    return Err(());
+                };
+                if inputs.len() != arg_tys.len() {
+                    return Err(());
+                }
+
+                let ocx = ObligationCtxt::new(&self.infcx);
+                let cause = ObligationCause::dummy();
+                for (&input, &arg_ty) in inputs.iter().zip(arg_tys) {
+                    let input = ocx.normalize(&cause, self.param_env, input);
+                    ocx.eq(&cause, self.param_env, input, arg_ty)
+                        .map_err(|_| ())?;
+                }
+                let predicates = self.tcx.predicates_of(method).instantiate(self.tcx, args);
+                for predicate in predicates.predicates {
+                    let predicate = ocx.normalize(&cause, self.param_env, predicate);
+                    ocx.register_obligation(Obligation::new(
+                        self.tcx,
+                        cause.clone(),
+                        self.param_env,
+                        predicate,
+                    ));
+                }
+                if !ocx.select_where_possible().is_empty() {
+                    return Err(());
+                }
+                Ok(self.output_of(method, args))
+            })
+            .ok()
+    }
+
+    /// Checks the adapter `name` against the semantics declared for an iterator of
+    /// another crate: whether it is indexed, and the methods it has if they are listed.
+    /// Methods which aren't rayon's leave the iterator, like in [`ParChain::step`].
+    fn step_declared(&mut self, source: &ParSource, name: &str) -> bool {
+        let is_par_iter_method = self.method(self.par_iter_trait, name).is_some();
+        let is_indexed_method =
+            !is_par_iter_method && self.method(self.indexed_trait, name).is_some();
+        if is_indexed_method && source.indexed != Some(true) {
+            return false;
+        }
+        if source.methods.is_empty() || source.methods.contains(&name) {
+            return true;
+        }
+        if is_par_iter_method || is_indexed_method {
+            return false;
+        }
+        self.declared = None;
+        true
+    }
+
+    /// The methods declared for the iterator of another crate, which the sequential
+    /// chain may call although rayon's iterators don't have them.
+    pub(crate) fn declared_methods(&self) -> &'static [&'static str] {
+        self.declared.map_or(&[], |source| source.methods)
+    }
+
+    /// Stops following the chain, e.g. when leaving a closure for an enclosing chain.
+    pub(crate) fn stop(&mut self) {
+        self.current = None;
+        self.declared = None;
+    }
+
+    /// Whether the chain is still followed, as opposed to stopped or left for methods
+    /// which aren't rayon's.
+    pub(crate) fn is_followed(&self) -> bool {
+        self.current.is_some() || self.declared.is_some()
+    }
+
+    /// Checks that the chain is still followed and produces a parallel iterator,
+    /// an indexed one if `indexed` is set.
+    pub(crate) fn produces(&self, indexed: bool) -> bool {
+        if let Some(source) = self.declared {
+            return !indexed || source.indexed == Some(true);
+        }
+        let trait_def_id = if indexed {
+            self.indexed_trait
+        } else {
+            self.par_iter_trait
+        };
+        self.current.is_some_and(|current| {
+            self.infcx
+                .type_implements_trait(trait_def_id, [current], self.param_env)
+                .may_apply()
+        })
+    }
+
+    fn method(&self, trait_def_id: DefId, name: &str) -> Option<DefId> {
+        self.tcx
+            .associated_items(trait_def_id)
+            .filter_by_name_unhygienic(Symbol::intern(name))
+            .find(|item| matches!(item.kind, ty::AssocKind::Fn))
+            .map(|item| item.def_id)
+    }
+
+    /// The generic arguments of `method` called on `self_ty`, with inference variables
+    /// for the generic parameters which depend on the arguments, such as closure types.
+    fn fresh_args(&self, method: DefId, self_ty: Ty<'tcx>) -> GenericArgsRef<'tcx> {
+        GenericArgs::for_item(self.tcx, method, |param, _| {
+            if param.index == 0 {
+                self_ty.into()
+            } else {
+                self.infcx.var_for_def(DUMMY_SP, param)
+            }
+        })
+    }
+
+    /// The return type of `method` called on `self_ty`, whatever its arguments.
+    fn output(&self, method: DefId, self_ty: Ty<'tcx>) -> Ty<'tcx> {
+        self.output_of(method, self.fresh_args(method, self_ty))
+    }
+
+    /// The return type of `method` instantiated with `args`.
+    fn output_of(&self, method: DefId, args: GenericArgsRef<'tcx>) -> Ty<'tcx> {
+        let sig = self.tcx.fn_sig(method).instantiate(self.tcx, args);
+        let output = self.tcx.instantiate_bound_regions_with_erased(sig.output());
+        let output = self
+            .infcx
+            .at(&ObligationCause::dummy(), self.param_env)
+            .normalize(output)
+            .value;
+        self.infcx.resolve_vars_if_possible(output)
+    }
+}
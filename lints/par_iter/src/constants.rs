@@ -1,7 +0,0 @@
-pub(crate) const TRAIT_PATHS: &[&[&str]] = &[
-    &["rayon", "iter", "IntoParallelIterator"],
-    &["rayon", "iter", "ParallelIterator"],
-    &["rayon", "iter", "IndexedParallelIterator"],
-    &["rayon", "iter", "IntoParallelRefMutIterator"],
-    &["rayon", "iter", "IntoParallelRefIterator"],
-];
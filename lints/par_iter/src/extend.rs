@@ -0,0 +1,131 @@
+use clippy_utils::ty::{implements_trait, is_type_diagnostic_item};
+use clippy_utils::{get_trait_def_id, SpanlessEq};
+use rustc_hir::{self as hir, Node};
+use rustc_lint::{LateContext, LintContext};
+use rustc_middle::ty;
+use rustc_span::{sym, Span, Symbol};
+use utils::span_to_snippet_macro;
+use utils::variable_check::check_trait_impl;
+
+use crate::chain::ParChain;
+
+/// Collections whose items end up in the order they were added in.
+const ORDERED_COLLECTIONS: &[rustc_span::Symbol] =
+    &[sym::Vec, sym::VecDeque, sym::LinkedList, sym::String];
+
+/// Edits turning a call to `Extend::extend` around the chain into its rayon
+/// counterpart.
+pub(crate) struct ExtendCall {
+    pub(crate) suggestions: Vec<(Span, String)>,
+    /// The method of rayon's traits replacing `extend`.
+    pub(crate) method: Symbol,
+    /// Whether the extended collection keeps the order of the items, which rayon does
+    /// as well.
+    pub(crate) ordered: bool,
+    pub(crate) note: Option<String>,
+}
+
+/// Checks whether `call` is `Extend::extend` on a collection implementing rayon's
+/// `ParallelExtend`, with `arg`, the end of the chain, as its argument. A `Vec` cleared
+/// by the statement before gets the items with `collect_into_vec` if the chain is
+/// indexed, and with `par_extend` otherwise.
+pub(crate) fn extend_call<'tcx>(
+    cx: &LateContext<'tcx>,
+    call: &'tcx hir::Expr<'tcx>,
+    arg: &'tcx hir::Expr<'tcx>,
+    chain: &ParChain<'tcx>,
+) -> Option<ExtendCall> {
+    let hir::ExprKind::MethodCall(path, recv, [call_arg], _) = call.kind else {
+        return None;
+    };
+    let typeck = cx.typeck_results();
+    let extend_trait = get_trait_def_id(cx.tcx, &["core", "iter", "Extend"])?;
+    let par_extend_trait = get_trait_def_id(cx.tcx, &["rayon", "iter", "ParallelExtend"])?;
+    if call_arg.hir_id != arg.hir_id
+        || call.span.from_expansion()
+        || typeck
+            .type_dependent_def_id(call.hir_id)
+            .and_then(|def_id| cx.tcx.trait_of_item(def_id))
+            != Some(extend_trait)
+    {
+        return None;
+    }
+
+    // The chain may end in a collection, e.g. once `collect` was suggested for a loop,
+    // which `par_extend` takes as well.
+    let into_iter_trait = cx.tcx.get_diagnostic_item(sym::IntoIterator)?;
+    let item_ty = cx.get_associated_type(typeck.expr_ty(arg), into_iter_trait, "Item")?;
+    let collection_ty = typeck.expr_ty_adjusted(recv).peel_refs();
+    if !implements_trait(cx, collection_ty, par_extend_trait, &[item_ty.into()]) {
+        return None;
+    }
+    let ordered = ORDERED_COLLECTIONS
+        .iter()
+        .any(|&name| is_type_diagnostic_item(cx, collection_ty, name));
+
+    if chain.produces(true)
+        && check_trait_impl(cx, typeck.expr_ty(arg), sym::Iterator)
+        && let Some(clear) = cleared_before(cx, call, recv)
+        && let ty::Adt(_, args) = collection_ty.kind()
+        && is_type_diagnostic_item(cx, collection_ty, sym::Vec)
+        && cx.tcx.erase_regions(args.type_at(0)) == cx.tcx.erase_regions(item_ty)
+    {
+        let recv_snippet = span_to_snippet_macro(cx.sess().source_map(), recv.span);
+        let target = if typeck.expr_ty(recv).is_ref() {
+            recv_snippet.clone()
+        } else {
+            format!("&mut {recv_snippet}")
+        };
+        return Some(ExtendCall {
+            suggestions: vec![
+                (clear, String::new()),
+                (call.span.until(arg.span), String::new()),
+                (
+                    call.span.with_lo(arg.span.hi()),
+                    format!(".collect_into_vec({target})"),
+                ),
+            ],
+            method: Symbol::intern("collect_into_vec"),
+            ordered,
+            note: Some(format!(
+                "`{recv_snippet}` is cleared before it is extended, so `collect_into_vec` \
+                 can write the items into it directly"
+            )),
+        });
+    }
+
+    Some(ExtendCall {
+        suggestions: vec![(path.ident.span, "par_extend".to_string())],
+        method: Symbol::intern("par_extend"),
+        ordered,
+        note: None,
+    })
+}
+
+/// If the statement of `call` comes right after a statement clearing `recv`, returns
+/// the span to remove that statement with, up to the statement of `call`.
+fn cleared_before<'tcx>(
+    cx: &LateContext<'tcx>,
+    call: &'tcx hir::Expr<'tcx>,
+    recv: &'tcx hir::Expr<'tcx>,
+) -> Option<Span> {
+    let Node::Stmt(stmt) = cx.tcx.parent_hir_node(call.hir_id) else {
+        return None;
+    };
+    let Node::Block(block) = cx.tcx.parent_hir_node(stmt.hir_id) else {
+        return None;
+    };
+    let index = block.stmts.iter().position(|s| s.hir_id == stmt.hir_id)?;
+    let previous = block.stmts.get(index.checked_sub(1)?)?;
+    if let hir::StmtKind::Semi(clear) = previous.kind
+        && let hir::ExprKind::MethodCall(clear_path, clear_recv, [], _) = clear.kind
+        && clear_path.ident.as_str() == "clear"
+        && !previous.span.from_expansion()
+        && SpanlessEq::new(cx)
+            .deny_side_effects()
+            .eq_expr(clear_recv, recv)
+    {
+        return Some(previous.span.until(stmt.span));
+    }
+    None
+}
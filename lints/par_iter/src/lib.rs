@@ -5,34 +5,47 @@
 
 extern crate rustc_data_structures;
 extern crate rustc_errors;
-extern crate rustc_hash;
 extern crate rustc_hir;
-extern crate rustc_hir_typeck;
 extern crate rustc_infer;
 extern crate rustc_middle;
 extern crate rustc_span;
 extern crate rustc_trait_selection;
 
-mod constants;
+mod bridge;
+mod chain;
+mod extend;
+mod multi_source;
+mod nesting;
+mod ordering;
+mod registry;
+mod returns;
+mod thread_scope;
 mod variable_check;
 
-use clippy_utils::{get_parent_expr, get_trait_def_id};
+use chain::{ParChain, Step};
+use clippy_utils::{get_parent_expr, get_trait_def_id, peel_blocks};
+use multi_source::{convert_argument, multi_source};
+use nesting::{Decision, NestedPolicy, Nesting};
+use ordering::Consumer;
 use rustc_data_structures::fx::FxHashSet;
 use rustc_errors::Applicability;
-use rustc_hir::intravisit::{walk_expr, Visitor};
-use rustc_hir::{self as hir};
-use rustc_infer::infer::TyCtxtInferExt;
-use rustc_infer::traits::{Obligation, ObligationCause};
+use rustc_hir::intravisit::Visitor;
+use rustc_hir::{self as hir, HirId};
 use rustc_lint::{LateContext, LateLintPass, LintContext};
-use rustc_middle::ty::{self, GenericArgs};
-use rustc_span::sym;
-use rustc_trait_selection::traits::ObligationCtxt;
-use variable_check::{
-    check_implements_par_iter, check_trait_impl, check_variables, generate_suggestion,
-    is_type_valid,
-};
-
-dylint_linting::declare_late_lint! {
+use rustc_middle::ty;
+use rustc_span::{sym, Span};
+use serde::Deserialize;
+use thread_scope::Backend;
+use utils::cost::{chain_cost, DEFAULT_BRIDGE_MIN_COST, DEFAULT_MIN_COST};
+use utils::par_chains::record_par_chain;
+use utils::par_modules::{iter_method_traits, record_par_suggestion, ParUse};
+use utils::par_sources::{par_source_with, seq_source_names, ParSource};
+use utils::rayon_crate::skip_without_rayon;
+use utils::validator::Validator;
+use utils::variable_check::{check_trait_impl, is_type_valid};
+use variable_check::generate_suggestion;
+
+dylint_linting::impl_late_lint! {
     /// ### What it does
     /// parallelize iterators using rayon
     /// ### Why is this bad?
@@ -52,171 +65,595 @@ dylint_linting::declare_late_lint! {
     /// ```
     pub PAR_ITER,
     Warn,
-    "suggest using par iter"
+    "suggest using par iter",
+    ParIter::new()
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct Config {
+    /// Chains whose estimated cost is below this are left sequential.
+    min_cost: u64,
+    /// The same for chains starting at an iterator without a parallel counterpart,
+    /// which would go through `par_bridge`.
+    bridge_min_cost: u64,
+    /// Which iterators of a nest are parallelized.
+    nested: NestedPolicy,
+    /// Parallel traits of other crates, tried before rayon's.
+    traits: Vec<registry::ParTrait>,
+    /// What the chains are parallelized with.
+    backend: Backend,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            min_cost: DEFAULT_MIN_COST,
+            bridge_min_cost: DEFAULT_BRIDGE_MIN_COST,
+            nested: NestedPolicy::default(),
+            traits: Vec::new(),
+            backend: Backend::default(),
+        }
+    }
+}
+
+pub struct ParIter {
+    config: Config,
+    /// The sources of the parallel traits declared in the configuration.
+    registered: &'static [ParSource],
+    candidates: Vec<Candidate>,
+}
+
+impl ParIter {
+    #[must_use]
+    pub fn new() -> Self {
+        let mut config: Config = dylint_linting::config_or_default(env!("CARGO_PKG_NAME"));
+        ParIter {
+            registered: registry::registry(core::mem::take(&mut config.traits)),
+            config,
+            candidates: Vec::new(),
+        }
+    }
 }
 
 impl<'tcx> LateLintPass<'tcx> for ParIter {
-    // TODO: implement check crate to check if rayon is present
-    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx hir::Expr<'_>) {
-        if let hir::ExprKind::MethodCall(path, recv, _args, _span) = &expr.kind
-            && let Some(suggestion) = generate_suggestion(cx, expr, path)
-        {
-            let par_iter_traits = check_implements_par_iter(cx, recv);
-            if !par_iter_traits.is_empty() && is_type_valid(cx, cx.typeck_results().expr_ty(recv)) {
-                // TODO: issue with into_par_iter() need to check directly with
-                // parallel iterator
-
-                let mut allowed_methods: FxHashSet<&str> =
-                    ["into_iter", "iter", "iter_mut", "map_or"]
-                        .into_iter()
-                        .collect();
-                for into_par_iter_trait in par_iter_traits {
-                    allowed_methods.extend(get_all_methods(cx, into_par_iter_trait, recv));
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx hir::Expr<'tcx>) {
+        if let Backend::ThreadScope = self.config.backend {
+            self.check_thread_scope(cx, expr);
+            return;
+        }
+
+        // The chain starts either at a source with a parallel counterpart, or at any other
+        // iterator which `par_bridge` can hand out to the worker threads.
+        let (start, suggestion, mut chain, bridged, source_trait) =
+            if let hir::ExprKind::MethodCall(path, recv, _args, _span) = &expr.kind
+                && let Some(call) = par_source_with(cx, expr, self.registered)
+            {
+                let Some(suggestion) = generate_suggestion(path, call.source) else {
+                    return;
+                };
+                let Some(chain) = ParChain::new(cx, &call) else {
+                    return;
+                };
+                if !is_type_valid(cx, cx.typeck_results().expr_ty(recv)) {
+                    return;
                 }
+                (*recv, suggestion, chain, false, call.trait_def_id)
+            } else if bridge::is_bridge_source(cx, expr, self.registered)
+                && let Some(chain) = ParChain::bridge(cx, cx.typeck_results().expr_ty(expr))
+                && let Some(bridge_trait) =
+                    get_trait_def_id(cx.tcx, &["rayon", "iter", "ParallelBridge"])
+                && let Ok(snippet) = cx.sess().source_map().span_to_snippet(expr.span)
+            {
+                (
+                    expr,
+                    (expr.span, format!("{snippet}.par_bridge()")),
+                    chain,
+                    true,
+                    bridge_trait,
+                )
+            } else {
+                return;
+            };
+
+        let seq_names: FxHashSet<&str> = seq_source_names()
+            .chain(self.registered.iter().map(|source| source.seq))
+            .collect();
+        let mut allowed_methods: FxHashSet<&str> =
+            seq_names.iter().copied().chain(["map_or"]).collect();
+        allowed_methods.extend(get_all_methods(cx));
+        allowed_methods.extend(chain.declared_methods());
 
-                let mut top_expr = *recv;
-                let mut found_iter_method = false;
-                let mut is_mut = false;
+        let mut top_expr = start;
+        // A bridged iterator is already the source of the chain.
+        let mut found_iter_method = bridged;
+        let mut is_mut = false;
+        // Conversions of the iterators given to `zip`, `chain` and the like.
+        let mut arguments = Vec::new();
+        // The `extend` call consuming the chain, if it is given to one.
+        let mut extend = None;
+        // The traits whose methods the parallel chain calls, which have to be in scope,
+        // and the methods of the parallel iterator it calls.
+        let mut par_traits = vec![source_trait];
+        let mut par_methods = Vec::new();
+        let mut flat_maps = Vec::new();
 
-                while let Some(parent_expr) = get_parent_expr(cx, top_expr) {
-                    match parent_expr.kind {
-                        hir::ExprKind::MethodCall(method_name, _, _, _) => {
-                            if ["into_iter", "iter", "iter_mut"]
-                                .contains(&method_name.ident.as_str())
+        while let Some(parent_expr) = get_parent_expr(cx, top_expr) {
+            match parent_expr.kind {
+                hir::ExprKind::MethodCall(method_name, method_recv, method_args, _) => {
+                    let name = method_name.ident.as_str();
+                    // Only our chain can be given to `par_extend`, not an enclosing one.
+                    if method_recv.hir_id != top_expr.hir_id
+                        && chain.is_followed()
+                        && let Some(call) = extend::extend_call(cx, parent_expr, top_expr, &chain)
+                    {
+                        extend = Some(call);
+                        break;
+                    }
+                    if seq_names.contains(name) {
+                        if found_iter_method {
+                            break;
+                        }
+                        found_iter_method = true;
+                        if ["iter_mut", "chunks_mut"].contains(&name) {
+                            is_mut = true;
+                        }
+                    }
+                    if !allowed_methods.contains(method_name.ident.as_str()) {
+                        return;
+                    }
+                    // Whether rayon needs the iterator given to this method to be indexed,
+                    // if it combines two iterators like `zip`.
+                    let combined = multi_source(name).filter(|_| {
+                        check_trait_impl(
+                            cx,
+                            cx.typeck_results().expr_ty(method_recv),
+                            sym::Iterator,
+                        )
+                    });
+                    if parent_expr.hir_id != expr.hir_id {
+                        // Adapters of our chain have to exist on the parallel
+                        // iterator built so far, others belong to an enclosing chain.
+                        let followed = chain.is_followed();
+                        if method_recv.hir_id != top_expr.hir_id {
+                            // The other side of a `zip` is converted with its receiver.
+                            if combined.is_some()
+                                && method_args
+                                    .first()
+                                    .is_some_and(|arg| arg.hir_id == top_expr.hir_id)
                             {
-                                if found_iter_method {
-                                    break;
-                                }
-                                found_iter_method = true;
-                                if method_name.ident.as_str() == "iter_mut" {
-                                    is_mut = true;
-                                }
+                                return;
                             }
-                            if !allowed_methods.contains(method_name.ident.as_str()) {
+                            chain.stop();
+                        } else {
+                            let Some(step) = chain.step(name, &arg_types(cx, method_args)) else {
                                 return;
+                            };
+                            if let Step::FlatMapIter = step {
+                                flat_maps.push(FlatMap {
+                                    span: method_name.ident.span,
+                                    returned: method_args
+                                        .first()
+                                        .and_then(|arg| returned_source(cx, arg, self.registered)),
+                                });
+                            }
+                            if followed {
+                                par_methods.push(method_name.ident.name);
+                                if let Some(indexed) = combined {
+                                    let Some(argument) = method_args.first().and_then(|arg| {
+                                        convert_argument(cx, arg, indexed, self.registered)
+                                    }) else {
+                                        return;
+                                    };
+                                    is_mut |= argument.is_mut;
+                                    arguments.extend(argument.suggestions);
+                                    par_traits.extend(argument.traits);
+                                }
                             }
-                            top_expr = parent_expr;
                         }
-                        hir::ExprKind::Closure(_) => top_expr = parent_expr,
-                        _ => break,
                     }
+                    top_expr = parent_expr;
                 }
-
-                // TODO: find a way to deal with iterators returns
-                if check_trait_impl(cx, cx.typeck_results().expr_ty(top_expr), sym::Iterator) {
-                    return;
+                hir::ExprKind::Closure(_) => {
+                    chain.stop();
+                    top_expr = parent_expr;
                 }
+                _ => break,
+            }
+        }
 
-                let mut validator = Validator {
-                    cx,
-                    is_valid: true,
-                    is_mut,
-                };
-                validator.visit_expr(top_expr);
-                if !validator.is_valid {
-                    return;
+        // A chain which is still an iterator is either returned, in which case
+        // the function can return a parallel iterator instead, or given up on.
+        let top_ty = cx.typeck_results().expr_ty(top_expr);
+        let returned = if extend.is_none() && check_trait_impl(cx, top_ty, sym::Iterator) {
+            let par_iter_methods = trait_methods(cx, &["rayon", "iter", "ParallelIterator"]);
+            let Some(returned) = returns::returned_chain(cx, top_expr, &par_iter_methods) else {
+                return;
+            };
+            Some(returned)
+        } else {
+            None
+        };
+
+        let min_cost = if bridged {
+            self.config.bridge_min_cost
+        } else {
+            self.config.min_cost
+        };
+        let ordered_extend = extend.as_ref().is_some_and(|call| call.ordered);
+        let Some(work) = check_work(
+            cx,
+            expr,
+            top_expr,
+            is_mut,
+            bridged,
+            ordered_extend,
+            min_cost,
+        ) else {
+            return;
+        };
+
+        let mut suggestions = vec![suggestion];
+        suggestions.extend(arguments);
+        let mut callers = Vec::new();
+        if let Some(returned) = &returned {
+            suggestions.extend(returned.suggestions.iter().cloned());
+            par_traits.extend(returned.par_trait);
+            callers.extend(returned.callers.iter().map(|(hir_id, methods)| ParUse {
+                hir_id: *hir_id,
+                traits: iter_method_traits(cx, methods),
+            }));
+        }
+        if let Some(extend) = &extend {
+            suggestions.extend(extend.suggestions.iter().cloned());
+            par_methods.push(extend.method);
+        }
+        par_traits.extend(iter_method_traits(cx, &par_methods));
+        let mut uses = vec![ParUse {
+            hir_id: expr.hir_id,
+            traits: par_traits,
+        }];
+        uses.extend(callers);
+
+        let mut notes = vec![format!("estimated cost of the work: {}", work.cost)];
+        if bridged {
+            notes.push(
+                "`par_bridge` hands out the items in no particular order, and the parallel \
+                 iterator it returns can't be indexed"
+                    .to_string(),
+            );
+        }
+        notes.extend(work.notes);
+        notes.extend(extend.and_then(|call| call.note));
+        if let Some(returned) = returned {
+            notes.push(format!(
+                "`{}` is changed to return a parallel iterator, which all of its callers can \
+                 consume",
+                returned.fn_name
+            ));
+        }
+
+        // Whether to report it depends on the other chains it is nested with, so all of
+        // them are reported at the end of the crate.
+        self.candidates.push(Candidate {
+            hir_id: expr.hir_id,
+            span: expr.span,
+            nesting: nesting::nesting(cx, expr.hir_id),
+            closures: nesting::chain_closures(top_expr, expr),
+            notes,
+            help: "try using a parallel iterator",
+            suggestions,
+            applicability: work.applicability,
+            uses: Some(uses),
+            flat_maps,
+        });
+    }
+
+    fn check_crate_post(&mut self, cx: &LateContext<'tcx>) {
+        let candidates = core::mem::take(&mut self.candidates);
+        // The iterators returned by the closure of a `flat_map` aren't nested chains of
+        // their own, and leave the policy to the chain around them.
+        let returned: FxHashSet<HirId> = candidates
+            .iter()
+            .flat_map(|candidate| candidate.flat_maps.iter().filter_map(|f| f.returned))
+            .collect();
+        let mut decisions: Vec<Decision> = candidates
+            .iter()
+            .map(|candidate| {
+                let others = candidates.iter().filter(|other| {
+                    other.hir_id != candidate.hir_id && !returned.contains(&other.hir_id)
+                });
+                let in_candidate = others
+                    .clone()
+                    .any(|outer| !outer.closures.is_disjoint(&candidate.nesting.closures));
+                let has_inner =
+                    others.any(|inner| !candidate.closures.is_disjoint(&inner.nesting.closures));
+                nesting::decide(
+                    self.config.nested,
+                    candidate.nesting.in_parallel,
+                    in_candidate,
+                    has_inner,
+                )
+            })
+            .collect();
+        // Whenever that chain is reported, they are parallelized along with it, as
+        // rayon's `flat_map` needs parallel iterators.
+        let exempt: FxHashSet<HirId> = candidates
+            .iter()
+            .zip(&decisions)
+            .filter(|(_, decision)| matches!(decision, Decision::Report(_)))
+            .flat_map(|(candidate, _)| candidate.flat_maps.iter().filter_map(|f| f.returned))
+            .collect();
+        for (candidate, decision) in candidates.iter().zip(&mut decisions) {
+            if exempt.contains(&candidate.hir_id) {
+                *decision = Decision::Report(None);
+            }
+        }
+        let reported: FxHashSet<HirId> = candidates
+            .iter()
+            .zip(&decisions)
+            .filter(|(_, decision)| matches!(decision, Decision::Report(_)))
+            .map(|(candidate, _)| candidate.hir_id)
+            .collect();
+
+        for (candidate, decision) in candidates.iter().zip(decisions) {
+            let Decision::Report(nested_note) = decision else {
+                continue;
+            };
+            // rayon's `flat_map` needs the iterators returned by its closure to be
+            // parallelized as well, `flat_map_iter` takes sequential ones.
+            let mut suggestions = candidate.suggestions.clone();
+            for flat_map in &candidate.flat_maps {
+                if !flat_map
+                    .returned
+                    .is_some_and(|source| reported.contains(&source))
+                {
+                    suggestions.push((flat_map.span, "flat_map_iter".to_string()));
                 }
+            }
+            // Also when skipped for lack of rayon, so that the reductions of the chain
+            // aren't counted as well.
+            record_par_chain(cx, PAR_ITER, candidate.hir_id);
 
-                cx.span_lint(PAR_ITER, expr.span, |diag| {
+            if let Some(uses) = &candidate.uses {
+                if skip_without_rayon(cx, PAR_ITER, candidate.hir_id) {
+                    continue;
+                }
+                record_par_suggestion(cx, PAR_ITER, candidate.hir_id, uses);
+            }
+            cx.tcx
+                .node_span_lint(PAR_ITER, candidate.hir_id, candidate.span, |diag| {
                     diag.primary_message("found iterator that can be parallelized");
-                    diag.multipart_suggestion(
-                        "try using a parallel iterator",
-                        vec![(expr.span, suggestion)],
-                        Applicability::MachineApplicable,
-                    );
+                    for note in candidate.notes.iter().chain(&nested_note) {
+                        diag.note(note.clone());
+                    }
+                    diag.multipart_suggestion(candidate.help, suggestions, candidate.applicability);
                 });
-            }
         }
     }
 }
 
-struct Validator<'a, 'tcx> {
-    cx: &'a LateContext<'tcx>,
-    is_valid: bool,
-    is_mut: bool,
+impl ParIter {
+    /// Without rayon, only chains over slices which end in the same statement can be
+    /// split between threads. They are found with `std`'s types alone, so that crates
+    /// which don't depend on rayon get them too.
+    fn check_thread_scope<'tcx>(&mut self, cx: &LateContext<'tcx>, expr: &'tcx hir::Expr<'tcx>) {
+        let Some(scoped) = thread_scope::rewrite(cx, expr) else {
+            return;
+        };
+        let Some(work) = check_work(
+            cx,
+            expr,
+            scoped.top,
+            scoped.is_mut,
+            false,
+            false,
+            self.config.min_cost,
+        ) else {
+            return;
+        };
+
+        let mut notes = vec![format!("estimated cost of the work: {}", work.cost)];
+        notes.extend(work.notes);
+        self.candidates.push(Candidate {
+            hir_id: expr.hir_id,
+            span: expr.span,
+            nesting: nesting::nesting(cx, expr.hir_id),
+            closures: nesting::chain_closures(scoped.top, expr),
+            notes,
+            help: "try splitting the items between scoped threads",
+            suggestions: vec![(scoped.top.span, scoped.code)],
+            applicability: work.applicability,
+            uses: None,
+            flat_maps: Vec::new(),
+        });
+    }
 }
 
-impl<'a, 'tcx> hir::intravisit::Visitor<'_> for Validator<'a, 'tcx> {
-    fn visit_expr(&mut self, ex: &hir::Expr) {
-        if let hir::ExprKind::MethodCall(_method_name, _receiver, args, _span) = ex.kind {
-            if !self.is_valid {
-                return;
-            }
-            let ex_ty = self.cx.typeck_results().expr_ty(ex);
-            self.is_valid &= is_type_valid(self.cx, ex_ty);
+/// What splitting the work of a chain between threads changes.
+struct Work {
+    cost: u64,
+    notes: Vec<String>,
+    applicability: Applicability,
+}
 
-            for arg in args {
-                if let hir::ExprKind::Closure(closure) = arg.kind {
-                    let mut params = hir::HirIdSet::default();
-                    let body = self.cx.tcx.hir().body(closure.body);
+/// Checks that the closures of the chain from `expr` up to `top_expr` can run on other
+/// threads, and that there is enough work for it to pay off.
+fn check_work<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx hir::Expr<'tcx>,
+    top_expr: &'tcx hir::Expr<'tcx>,
+    is_mut: bool,
+    bridged: bool,
+    ordered_extend: bool,
+    min_cost: u64,
+) -> Option<Work> {
+    let mut validator = Validator::new(cx, is_mut);
+    if bridged {
+        validator.send_only = bridge::chain_calls(top_expr, expr);
+    }
+    validator.visit_expr(top_expr);
+    if !validator.is_valid {
+        return None;
+    }
 
-                    for param in body.params {
-                        if let hir::PatKind::Binding(_, hir_id, _, _) = param.pat.kind {
-                            params.insert(hir_id);
-                        }
-                    }
+    // Splitting the work between threads only pays off for enough of it.
+    let cost = chain_cost(cx, expr, top_expr);
+    if cost < min_cost {
+        return None;
+    }
 
-                    self.is_valid &=
-                        check_variables(self.cx, closure.def_id, body, &params, self.is_mut);
-                }
-            }
-        }
-        walk_expr(self, ex)
+    // Parallel iterators give no guarantee on the order in which items are
+    // processed, so only suggest it blindly if nothing can tell the difference.
+    // A bridge doesn't even keep the order of the items it collects.
+    let mut consumer = ordering::classify(cx, expr, top_expr);
+    // Rayon extends ordered collections in the order of the items, like `collect`.
+    if ordered_extend && matches!(consumer, Consumer::OrderInsensitive) {
+        consumer = Consumer::OrderPreserving;
+    }
+    let loses_order = match &consumer {
+        Consumer::OrderPreserving => bridged,
+        Consumer::OrderInsensitive => false,
+        Consumer::OrderDependent(_) => true,
+    };
+    let mut notes = Vec::new();
+    if let Consumer::OrderDependent(culprit) = consumer {
+        notes.push(format!(
+            "{culprit} depends on the iteration order, which parallel iterators don't \
+             preserve"
+        ));
     }
+    // Each of the worker threads sees its own value of a thread local.
+    if let Some(name) = validator.thread_local {
+        notes.push(format!(
+            "`{name}` is a thread local, which has a separate value on each thread"
+        ));
+    }
+    let applicability = if loses_order || validator.thread_local.is_some() {
+        Applicability::MaybeIncorrect
+    } else {
+        Applicability::MachineApplicable
+    };
+    Some(Work {
+        cost,
+        notes,
+        applicability,
+    })
+}
+
+/// A chain which can be parallelized, waiting for the policy on nested chains.
+struct Candidate {
+    hir_id: HirId,
+    span: Span,
+    nesting: Nesting,
+    /// The closures given to the chain, which other chains may be nested in.
+    closures: FxHashSet<HirId>,
+    notes: Vec<String>,
+    help: &'static str,
+    suggestions: Vec<(Span, String)>,
+    applicability: Applicability,
+    /// With the rayon backend, the places whose new code calls methods of rayon's
+    /// traits: the chain, and the callers of a function changed to return it.
+    uses: Option<Vec<ParUse>>,
+    /// The `flat_map`s of the chain whose closures return sequential iterators.
+    flat_maps: Vec<FlatMap>,
+}
+
+/// A `flat_map` whose closure returns a sequential iterator, which becomes a
+/// `flat_map_iter` unless that iterator is reported as well.
+struct FlatMap {
+    /// The name of the method.
+    span: Span,
+    /// The source of the chain the closure returns.
+    returned: Option<HirId>,
 }
 
-fn get_all_methods<'tcx>(
+/// The source of the chain returned by the closure `arg`, if rayon has a counterpart
+/// for it.
+fn returned_source<'tcx>(
     cx: &LateContext<'tcx>,
-    into_iter_trait: hir::def_id::DefId,
-    original_expr: &hir::Expr,
-) -> Vec<&'tcx str> {
-    let mut res = Vec::new();
-    if let (Some(parallel_iterator_def_id), Some(parallel_indexed_iterator_def_id)) = (
-        get_trait_def_id(cx.tcx, &["rayon", "iter", "ParallelIterator"]),
-        get_trait_def_id(cx.tcx, &["rayon", "iter", "IndexedParallelIterator"]),
-    ) {
-        let tcx = cx.tcx;
-        let infcx = tcx.infer_ctxt().build();
-        let ocx = ObligationCtxt::new(&infcx);
-        let param_env = tcx.param_env(into_iter_trait);
-
-        // Create a new inference variable, ?new
-        let ty = infcx.next_ty_var(original_expr.span);
-
-        let projection = ty::Binder::dummy(ty::PredicateKind::Clause(ty::ClauseKind::Projection(
-            ty::ProjectionPredicate {
-                projection_term: ty::AliasTerm::new(tcx, into_iter_trait, GenericArgs::empty()),
-                term: ty.into(),
-            },
-        )));
-
-        let obligation = Obligation::new(tcx, ObligationCause::dummy(), param_env, projection);
-
-        ocx.register_obligation(obligation);
-
-        // let errors = ocx.select_where_possible();
-        // if errors.is_empty() {
-        //     dbg!("no errors"); // TODO: do something else here
-        // }
-
-        // TODO: use the previous steps to determine which ids should be run
-        let ids = &[parallel_iterator_def_id, parallel_indexed_iterator_def_id];
-        for def_id in ids {
-            let associated_items = cx.tcx.associated_items(def_id);
-            // Filter out only methods from the associated items
-            let methods: Vec<&str> = associated_items
-                .in_definition_order()
-                .filter(|item| matches!(item.kind, ty::AssocKind::Fn))
-                .map(|item| item.name.as_str())
-                .collect();
-            res.extend(methods);
+    arg: &'tcx hir::Expr<'tcx>,
+    registered: &'static [ParSource],
+) -> Option<HirId> {
+    let hir::ExprKind::Closure(closure) = arg.kind else {
+        return None;
+    };
+    let mut expr = peel_blocks(cx.tcx.hir().body(closure.body).value);
+    while let hir::ExprKind::MethodCall(_, recv, _, _) = expr.kind {
+        if par_source_with(cx, expr, registered).is_some() {
+            return Some(expr.hir_id);
         }
+        expr = recv;
     }
+    None
+}
 
-    res
+/// The types of the arguments of a method call, after coercions.
+pub(crate) fn arg_types<'tcx>(
+    cx: &LateContext<'tcx>,
+    args: &[hir::Expr<'tcx>],
+) -> Vec<ty::Ty<'tcx>> {
+    args.iter()
+        .map(|arg| cx.typeck_results().expr_ty_adjusted(arg))
+        .collect()
+}
+
+/// Names of all methods of rayon's parallel iterators.
+fn get_all_methods<'tcx>(cx: &LateContext<'tcx>) -> Vec<&'tcx str> {
+    let mut methods = trait_methods(cx, &["rayon", "iter", "ParallelIterator"]);
+    methods.extend(trait_methods(
+        cx,
+        &["rayon", "iter", "IndexedParallelIterator"],
+    ));
+    methods
+}
+
+fn trait_methods<'tcx>(cx: &LateContext<'tcx>, path: &[&str]) -> Vec<&'tcx str> {
+    get_trait_def_id(cx.tcx, path).map_or_else(Vec::new, |def_id| {
+        cx.tcx
+            .associated_items(def_id)
+            .in_definition_order()
+            .filter(|item| matches!(item.kind, ty::AssocKind::Fn))
+            .map(|item| item.name.as_str())
+            .collect()
+    })
 }
 
 #[test]
 fn ui() {
-    dylint_testing::ui_test_examples(env!("CARGO_PKG_NAME"));
+    // The other examples work on a handful of items, so they run without a threshold.
+    for example in [
+        "par_iter_main",
+        "par_iter_main2",
+        "par_iter_sources",
+        "par_iter_returns",
+        "par_iter_globals",
+        "par_iter_multi_source",
+        "par_iter_nested",
+        "par_iter_extend",
+        "par_iter_renaming",
+    ] {
+        dylint_testing::ui::Test::example(env!("CARGO_PKG_NAME"), example)
+            .dylint_toml("par_iter.min_cost = 0")
+            .run();
+    }
+    dylint_testing::ui::Test::example(env!("CARGO_PKG_NAME"), "par_iter_nested_innermost")
+        .dylint_toml("par_iter.min_cost = 0\npar_iter.nested = \"innermost\"")
+        .run();
+    dylint_testing::ui::Test::example(env!("CARGO_PKG_NAME"), "par_iter_registry")
+        .dylint_toml(
+            r#"[par_iter]
+min_cost = 0
+traits = [
+    { path = "par_iter_registry::collections::ParItems", seq = "items", par = "par_items", by_ref = true },
+    { path = "par_iter_registry::collections::ParShards", seq = "shards", par = "par_shards", by_ref = true, indexed = false, methods = ["for_each"] },
+]"#,
+        )
+        .run();
+    dylint_testing::ui::Test::example(env!("CARGO_PKG_NAME"), "par_iter_thread_scope")
+        .dylint_toml("par_iter.min_cost = 0\npar_iter.backend = \"thread_scope\"")
+        .run();
+    for example in ["par_iter_cost", "par_iter_bridge"] {
+        dylint_testing::ui::Test::example(env!("CARGO_PKG_NAME"), example).run();
+    }
 }
@@ -0,0 +1,105 @@
+use clippy_utils::get_trait_def_id;
+use rustc_hir as hir;
+use rustc_hir::def_id::DefId;
+use rustc_lint::LateContext;
+use rustc_span::Span;
+use utils::par_modules::iter_method_traits;
+use utils::par_sources::{implements_rayon_trait, par_source_with, ParSource};
+
+use crate::arg_types;
+use crate::chain::{ParChain, Step};
+use crate::variable_check::generate_suggestion;
+
+/// Methods combining the chain with the iterator given as their first argument, and
+/// whether rayon needs that iterator to be indexed.
+const MULTI_SOURCE_METHODS: &[(&str, bool)] = &[
+    ("zip", true),
+    ("interleave", true),
+    ("chain", false),
+    ("eq", true),
+    ("cmp", true),
+    ("partial_cmp", true),
+];
+
+/// The other side of a multi-source method, converted to a parallel iterator.
+pub(crate) struct Argument {
+    pub(crate) suggestions: Vec<(Span, String)>,
+    /// Whether it iterates mutably, like `iter_mut`.
+    pub(crate) is_mut: bool,
+    /// The traits of the renamed source and of the adapters on it.
+    pub(crate) traits: Vec<DefId>,
+}
+
+/// If `name` combines the chain with another iterator, returns whether that iterator
+/// has to be indexed.
+pub(crate) fn multi_source(name: &str) -> Option<bool> {
+    MULTI_SOURCE_METHODS
+        .iter()
+        .find(|(method, _)| *method == name)
+        .map(|&(_, indexed)| indexed)
+}
+
+/// Converts `arg`, the iterator given to a multi-source method, to the matching parallel
+/// iterator: a chain of adapters on a source with a rayon counterpart is renamed, and a
+/// value which rayon takes as it is, such as `&Vec<T>`, is kept. Returns `None` when the
+/// argument can't be given to rayon, or isn't indexed although `indexed` is set. The
+/// sources of `registered` are tried along with rayon's.
+pub(crate) fn convert_argument<'tcx>(
+    cx: &LateContext<'tcx>,
+    arg: &'tcx hir::Expr<'tcx>,
+    indexed: bool,
+    registered: &'static [ParSource],
+) -> Option<Argument> {
+    // The adapters on the source and the types of their arguments, outermost first.
+    let mut adapters = Vec::new();
+    let mut expr = arg;
+    loop {
+        if let hir::ExprKind::MethodCall(path, _, _, _) = expr.kind
+            && let Some(call) = par_source_with(cx, expr, registered)
+        {
+            let mut chain = ParChain::new(cx, &call)?;
+            for (name, arg_tys) in adapters.iter().rev() {
+                if !matches!(chain.step(name.as_str(), arg_tys), Some(Step::Same)) {
+                    return None;
+                }
+            }
+            if !chain.produces(indexed) {
+                return None;
+            }
+            let suggestion = generate_suggestion(path, call.source)?;
+            let mut traits = vec![call.trait_def_id];
+            let names: Vec<_> = adapters.iter().map(|&(name, _)| name).collect();
+            traits.extend(iter_method_traits(cx, &names));
+            return Some(Argument {
+                suggestions: vec![suggestion],
+                is_mut: ["iter_mut", "chunks_mut"].contains(&call.source.seq),
+                traits,
+            });
+        }
+        match expr.kind {
+            hir::ExprKind::MethodCall(path, recv, args, _) => {
+                adapters.push((path.ident.name, arg_types(cx, args)));
+                expr = recv;
+            }
+            _ => break,
+        }
+    }
+
+    // Only the value itself can be given to rayon without a source call.
+    if !adapters.is_empty() {
+        return None;
+    }
+    let ty = cx.typeck_results().expr_ty(arg);
+    let into_trait = get_trait_def_id(cx.tcx, &["rayon", "iter", "IntoParallelIterator"])?;
+    if !implements_rayon_trait(cx, ty, into_trait) {
+        return None;
+    }
+    if !ParChain::into_par_iter(cx, ty)?.produces(indexed) {
+        return None;
+    }
+    Some(Argument {
+        suggestions: Vec::new(),
+        is_mut: false,
+        traits: Vec::new(),
+    })
+}
@@ -0,0 +1,239 @@
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{walk_body, walk_expr, Visitor};
+use rustc_hir::{self as hir, HirId, Node};
+use rustc_lint::LateContext;
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::TypeckResults;
+use rustc_span::sym;
+use serde::Deserialize;
+
+/// How deep the callers of a function are followed.
+const MAX_DEPTH: usize = 4;
+
+/// Which iterators of a nest get parallelized, since running parallel iterators in
+/// the closures of other parallel iterators oversubscribes rayon's threads.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NestedPolicy {
+    /// Only the iterators which aren't nested in another one.
+    #[default]
+    Outermost,
+    /// Only the iterators which have no other one nested in them.
+    Innermost,
+    /// All of them.
+    Both,
+}
+
+impl NestedPolicy {
+    fn name(self) -> &'static str {
+        match self {
+            NestedPolicy::Outermost => "outermost",
+            NestedPolicy::Innermost => "innermost",
+            NestedPolicy::Both => "both",
+        }
+    }
+}
+
+/// Where an iterator chain sits with respect to the iterators around it.
+#[derive(Default)]
+pub(crate) struct Nesting {
+    /// Runs in a closure given to rayon, directly or through the callers of its
+    /// function.
+    pub(crate) in_parallel: bool,
+    /// The closures of sequential iterator chains it runs in.
+    pub(crate) closures: FxHashSet<HirId>,
+}
+
+/// Finds the iterators around `hir_id`: the closures it is written in and, once the
+/// function is reached, the closures all of its callers in the crate are written in.
+pub(crate) fn nesting(cx: &LateContext<'_>, hir_id: HirId) -> Nesting {
+    nesting_at(cx, hir_id, &mut FxHashSet::default(), 0)
+}
+
+fn nesting_at(
+    cx: &LateContext<'_>,
+    hir_id: HirId,
+    visited: &mut FxHashSet<LocalDefId>,
+    depth: usize,
+) -> Nesting {
+    let mut nesting = Nesting::default();
+    for (id, node) in cx.tcx.hir().parent_iter(hir_id) {
+        match node {
+            Node::Expr(hir::Expr {
+                kind: hir::ExprKind::Closure(_),
+                ..
+            }) => {
+                let Node::Expr(call) = cx.tcx.parent_hir_node(id) else {
+                    continue;
+                };
+                let typeck = cx.tcx.typeck(call.hir_id.owner.def_id);
+                let Some(callee) = callee(typeck, call) else {
+                    continue;
+                };
+                if cx
+                    .tcx
+                    .crate_name(callee.krate)
+                    .as_str()
+                    .starts_with("rayon")
+                {
+                    nesting.in_parallel = true;
+                } else if cx.tcx.trait_of_item(callee) == cx.tcx.get_diagnostic_item(sym::Iterator)
+                {
+                    nesting.closures.insert(id);
+                }
+            }
+            Node::Item(_) | Node::ImplItem(_) | Node::TraitItem(_) => {
+                // Functions on the way are skipped, to not follow recursive calls.
+                let fn_def_id = id.owner.def_id;
+                if depth < MAX_DEPTH
+                    && node.fn_decl().is_some()
+                    && !cx.effective_visibilities.is_exported(fn_def_id)
+                    && visited.insert(fn_def_id)
+                {
+                    if let Some(callers) = callers_nesting(cx, fn_def_id, visited, depth) {
+                        nesting.in_parallel |= callers.in_parallel;
+                        nesting.closures.extend(callers.closures);
+                    }
+                    visited.remove(&fn_def_id);
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+    nesting
+}
+
+/// What all the callers of `fn_def_id` in the crate have in common: whether they all
+/// run in parallel, and the closures they are all written in.
+fn callers_nesting(
+    cx: &LateContext<'_>,
+    fn_def_id: LocalDefId,
+    visited: &mut FxHashSet<LocalDefId>,
+    depth: usize,
+) -> Option<Nesting> {
+    let mut finder = CallSiteFinder {
+        cx,
+        fn_def_id: fn_def_id.to_def_id(),
+        typeck: None,
+        sites: Vec::new(),
+    };
+    cx.tcx.hir().visit_all_item_likes_in_crate(&mut finder);
+
+    let mut sites = finder.sites.into_iter();
+    let mut common = nesting_at(cx, sites.next()?, visited, depth + 1);
+    for site in sites {
+        let nesting = nesting_at(cx, site, visited, depth + 1);
+        common.in_parallel &= nesting.in_parallel;
+        common
+            .closures
+            .retain(|closure| nesting.closures.contains(closure));
+    }
+    Some(common)
+}
+
+/// The function or method called by `expr`, if it is a call.
+pub(crate) fn callee(typeck: &TypeckResults<'_>, expr: &hir::Expr<'_>) -> Option<DefId> {
+    match expr.kind {
+        hir::ExprKind::Call(func, _) => match func.kind {
+            hir::ExprKind::Path(ref qpath) => typeck.qpath_res(qpath, func.hir_id).opt_def_id(),
+            _ => None,
+        },
+        hir::ExprKind::MethodCall(..) => typeck.type_dependent_def_id(expr.hir_id),
+        _ => None,
+    }
+}
+
+struct CallSiteFinder<'a, 'tcx> {
+    cx: &'a LateContext<'tcx>,
+    fn_def_id: DefId,
+    typeck: Option<&'tcx TypeckResults<'tcx>>,
+    sites: Vec<HirId>,
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for CallSiteFinder<'a, 'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.cx.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: hir::BodyId) {
+        let old_typeck = self.typeck.replace(self.cx.tcx.typeck_body(body_id));
+        walk_body(self, self.cx.tcx.hir().body(body_id));
+        self.typeck = old_typeck;
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        if let Some(typeck) = self.typeck
+            && callee(typeck, expr) == Some(self.fn_def_id)
+        {
+            self.sites.push(expr.hir_id);
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Whether a chain is reported, and with which note on its nesting.
+pub(crate) enum Decision {
+    Skip,
+    Report(Option<String>),
+}
+
+/// Applies `policy` to a chain which runs in parallel code if `in_parallel`, in the
+/// closures of another reported chain if `in_candidate`, and has reported chains in
+/// its own closures if `has_inner`.
+pub(crate) fn decide(
+    policy: NestedPolicy,
+    in_parallel: bool,
+    in_candidate: bool,
+    has_inner: bool,
+) -> Decision {
+    let name = policy.name();
+    let note = match policy {
+        NestedPolicy::Outermost if in_parallel || in_candidate => return Decision::Skip,
+        NestedPolicy::Innermost if has_inner => return Decision::Skip,
+        _ if in_parallel => format!(
+            "this iterator already runs on rayon's threads, and is parallelized as well \
+             following the `nested = \"{name}\"` policy"
+        ),
+        NestedPolicy::Innermost if in_candidate => format!(
+            "the iterator around this one is left sequential, following the \
+             `nested = \"{name}\"` policy"
+        ),
+        NestedPolicy::Both if in_candidate => format!(
+            "the iterator around this one is parallelized as well, following the \
+             `nested = \"{name}\"` policy"
+        ),
+        NestedPolicy::Outermost if has_inner => format!(
+            "the iterators nested in this one are left sequential, following the \
+             `nested = \"{name}\"` policy"
+        ),
+        NestedPolicy::Both if has_inner => format!(
+            "the iterators nested in this one are parallelized as well, following the \
+             `nested = \"{name}\"` policy"
+        ),
+        _ => return Decision::Report(None),
+    };
+    Decision::Report(Some(note))
+}
+
+/// The closures given to the methods of the chain from `source` up to `top`.
+pub(crate) fn chain_closures<'tcx>(
+    mut top: &'tcx hir::Expr<'tcx>,
+    source: &'tcx hir::Expr<'tcx>,
+) -> FxHashSet<HirId> {
+    let mut closures = FxHashSet::default();
+    while top.hir_id != source.hir_id
+        && let hir::ExprKind::MethodCall(_, recv, args, _) = top.kind
+    {
+        closures.extend(
+            args.iter()
+                .filter(|arg| matches!(arg.kind, hir::ExprKind::Closure(_)))
+                .map(|arg| arg.hir_id),
+        );
+        top = recv;
+    }
+    closures
+}
@@ -0,0 +1,102 @@
+use clippy_utils::is_trait_method;
+use clippy_utils::macros::root_macro_call;
+use clippy_utils::ty::is_type_diagnostic_item;
+use clippy_utils::visitors::for_each_expr;
+use core::ops::ControlFlow;
+use rustc_hir as hir;
+use rustc_lint::LateContext;
+use rustc_span::sym;
+
+/// How the consumer of an iterator chain depends on the order of its items.
+pub(crate) enum Consumer {
+    /// The items end up in the same order, e.g. `collect` into a `Vec`, which rayon
+    /// keeps ordered.
+    OrderPreserving,
+    /// The result doesn't depend on the order, e.g. `sum` or `count`.
+    OrderInsensitive,
+    /// Something observes the order of the items, e.g. `println!` or `push`.
+    /// Holds a description of the culprit.
+    OrderDependent(String),
+}
+
+/// Methods whose result depends on which item comes first.
+const ORDER_DEPENDENT_METHODS: &[&str] = &["find", "find_map", "position", "last", "nth"];
+
+/// Macros writing their output in call order.
+const ORDER_DEPENDENT_MACROS: &[&str] = &[
+    "print", "println", "eprint", "eprintln", "write", "writeln", "dbg", "trace", "debug", "info",
+    "warn", "error", "log",
+];
+
+/// Methods appending to an ordered collection.
+const ORDERED_SINK_METHODS: &[&str] = &["push", "push_back", "push_front", "push_str", "insert"];
+
+/// Classifies how the chain from `source`, the call producing the iterator, up to
+/// `top`, its consumer, depends on the order in which items are produced.
+pub(crate) fn classify<'tcx>(
+    cx: &LateContext<'tcx>,
+    source: &'tcx hir::Expr<'tcx>,
+    top: &'tcx hir::Expr<'tcx>,
+) -> Consumer {
+    // Hash based collections have no defined order to begin with.
+    if let hir::ExprKind::MethodCall(_, recv, _, _) = source.kind {
+        let recv_ty = cx.typeck_results().expr_ty(recv).peel_refs();
+        if is_type_diagnostic_item(cx, recv_ty, sym::HashMap)
+            || is_type_diagnostic_item(cx, recv_ty, sym::HashSet)
+        {
+            return Consumer::OrderInsensitive;
+        }
+    }
+
+    let mut consumer = Consumer::OrderInsensitive;
+    let mut expr = top;
+    while expr.hir_id != source.hir_id
+        && let hir::ExprKind::MethodCall(path, recv, args, _) = expr.kind
+    {
+        let name = path.ident.as_str();
+        if ORDER_DEPENDENT_METHODS.contains(&name) {
+            return Consumer::OrderDependent(format!("`{name}`"));
+        }
+        if name == "collect" {
+            consumer = Consumer::OrderPreserving;
+        }
+        for arg in args {
+            if let hir::ExprKind::Closure(closure) = arg.kind
+                && let Some(culprit) = order_dependent_effect(cx, cx.tcx.hir().body(closure.body))
+            {
+                return Consumer::OrderDependent(culprit);
+            }
+        }
+        expr = recv;
+    }
+    consumer
+}
+
+/// Looks for side effects in a closure which happen in iteration order, such as IO,
+/// logging or pushing into an ordered collection.
+fn order_dependent_effect<'tcx>(
+    cx: &LateContext<'tcx>,
+    body: &'tcx hir::Body<'tcx>,
+) -> Option<String> {
+    for_each_expr(cx, body.value, |e| {
+        if let Some(macro_call) = root_macro_call(e.span) {
+            let name = cx.tcx.item_name(macro_call.def_id);
+            if ORDER_DEPENDENT_MACROS.contains(&name.as_str()) {
+                return ControlFlow::Break(format!("`{name}!`"));
+            }
+        }
+        if let hir::ExprKind::MethodCall(path, recv, _, _) = e.kind {
+            let name = path.ident.as_str();
+            let recv_ty = cx.typeck_results().expr_ty_adjusted(recv).peel_refs();
+            let is_ordered_sink = [sym::Vec, sym::VecDeque, sym::LinkedList, sym::String]
+                .into_iter()
+                .any(|s| is_type_diagnostic_item(cx, recv_ty, s));
+            if (is_ordered_sink && ORDERED_SINK_METHODS.contains(&name))
+                || is_trait_method(cx, e, sym::IoWrite)
+            {
+                return ControlFlow::Break(format!("`{name}`"));
+            }
+        }
+        ControlFlow::Continue(())
+    })
+}
@@ -0,0 +1,50 @@
+use serde::Deserialize;
+use utils::par_sources::ParSource;
+
+/// A parallel trait of another crate, declared in the configuration: a collection
+/// implementing rayon's traits through a wrapper crate, a re-export of one of rayon's
+/// traits, or an iterator of its own with rayon's semantics.
+#[derive(Deserialize)]
+pub(crate) struct ParTrait {
+    /// The path of the trait, e.g. `"my_collections::ParItems"`.
+    path: String,
+    /// The sequential method which the trait's method replaces.
+    seq: String,
+    /// The method of the trait producing the parallel iterator.
+    par: String,
+    /// Whether the trait is implemented for the type behind the receiver's reference.
+    #[serde(default)]
+    by_ref: bool,
+    /// Number of arguments of the call.
+    #[serde(default)]
+    args: usize,
+    /// Whether the parallel iterator is indexed. Only needed when its type doesn't
+    /// implement rayon's traits.
+    indexed: Option<bool>,
+    /// The methods of the parallel iterator, all of rayon's if empty.
+    #[serde(default)]
+    methods: Vec<String>,
+}
+
+/// Turns the declared traits into sources which the lint tries before rayon's. The
+/// configuration is read once, so its strings are leaked to live as long as rayon's.
+pub(crate) fn registry(traits: Vec<ParTrait>) -> &'static [ParSource] {
+    traits
+        .into_iter()
+        .map(|par_trait| ParSource {
+            seq: par_trait.seq.leak(),
+            par: par_trait.par.leak(),
+            trait_path: par_trait.path.leak().split("::").collect::<Vec<_>>().leak(),
+            by_ref: par_trait.by_ref,
+            args: par_trait.args,
+            indexed: par_trait.indexed,
+            methods: par_trait
+                .methods
+                .into_iter()
+                .map(|method| &*method.leak())
+                .collect::<Vec<_>>()
+                .leak(),
+        })
+        .collect::<Vec<_>>()
+        .leak()
+}
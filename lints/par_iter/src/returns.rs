@@ -0,0 +1,216 @@
+use clippy_utils::ty::get_iterator_item_ty;
+use clippy_utils::{get_parent_expr, get_trait_def_id};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{walk_body, walk_expr, Visitor};
+use rustc_hir::{self as hir, HirId};
+use rustc_lint::LateContext;
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::TypeckResults;
+use rustc_span::{sym, Span, Symbol};
+use utils::global_effects::global_effects;
+use utils::variable_check::{check_trait_impl, check_variables, is_type_valid};
+
+use crate::nesting::callee;
+
+/// Sequential methods which take a different name on `ParallelIterator`.
+const CALLER_RENAMES: &[(&str, &str)] = &[
+    ("into_iter", "into_par_iter"),
+    ("find", "find_first"),
+    ("find_map", "find_map_first"),
+];
+
+/// Edits turning a function returning `impl Iterator` into one returning
+/// `impl ParallelIterator`.
+pub(crate) struct ReturnedChain {
+    pub(crate) fn_name: String,
+    pub(crate) suggestions: Vec<(Span, String)>,
+    /// `ParallelIterator`, named by the new return type.
+    pub(crate) par_trait: Option<DefId>,
+    /// The calls to the function, with the methods of the parallel iterator they call.
+    pub(crate) callers: Vec<(HirId, Vec<Symbol>)>,
+}
+
+/// Checks whether `top`, the end of a chain which is still an iterator, is returned
+/// by a private function with an `impl Iterator<Item = T>` return type, and whether
+/// every caller in the crate only consumes it with methods of `ParallelIterator`.
+/// If so, returns the edits to the signature and to the call sites.
+pub(crate) fn returned_chain<'tcx>(
+    cx: &LateContext<'tcx>,
+    top: &'tcx hir::Expr<'tcx>,
+    par_iter_methods: &[&str],
+) -> Option<ReturnedChain> {
+    let body_id = cx.enclosing_body?;
+    let body = cx.tcx.hir().body(body_id);
+    let is_tail = match body.value.kind {
+        hir::ExprKind::Block(block, _) => block.expr.is_some_and(|e| e.hir_id == top.hir_id),
+        _ => body.value.hir_id == top.hir_id,
+    };
+    if !is_tail {
+        return None;
+    }
+
+    let fn_def_id = cx.tcx.hir().body_owner_def_id(body_id);
+    if cx.effective_visibilities.is_exported(fn_def_id) {
+        return None;
+    }
+    let iterator_bound_span = iterator_bound_span(cx, fn_def_id)?;
+
+    // The items are produced on the worker threads.
+    let item_ty = get_iterator_item_ty(cx, cx.typeck_results().expr_ty(top))?;
+    if !check_trait_impl(cx, item_ty, sym::Send) {
+        return None;
+    }
+
+    let mut finder = CallerFinder {
+        cx,
+        fn_def_id: fn_def_id.to_def_id(),
+        par_iter_methods,
+        typeck: None,
+        calls: Vec::new(),
+        compatible: true,
+        suggestions: vec![(iterator_bound_span, "ParallelIterator".to_string())],
+    };
+    cx.tcx.hir().visit_all_item_likes_in_crate(&mut finder);
+    if finder.calls.is_empty() || !finder.compatible {
+        return None;
+    }
+
+    Some(ReturnedChain {
+        fn_name: cx.tcx.item_name(fn_def_id.to_def_id()).to_string(),
+        suggestions: finder.suggestions,
+        par_trait: get_trait_def_id(cx.tcx, &["rayon", "iter", "ParallelIterator"]),
+        callers: finder.calls,
+    })
+}
+
+/// Finds the `Iterator` in a return type of the shape `impl Iterator<Item = T> + 'a`.
+fn iterator_bound_span(cx: &LateContext<'_>, fn_def_id: LocalDefId) -> Option<Span> {
+    let decl = cx.tcx.hir_node_by_def_id(fn_def_id).fn_decl()?;
+    let hir::FnRetTy::Return(ret_ty) = decl.output else {
+        return None;
+    };
+    let hir::TyKind::OpaqueDef(item_id, _, _) = ret_ty.kind else {
+        return None;
+    };
+    let opaque = cx.tcx.hir().item(item_id).expect_opaque_ty();
+
+    let iterator = cx.tcx.get_diagnostic_item(sym::Iterator)?;
+    let mut span = None;
+    for bound in opaque.bounds {
+        match bound {
+            hir::GenericBound::Trait(poly, _)
+                if span.is_none() && poly.trait_ref.trait_def_id() == Some(iterator) =>
+            {
+                span = Some(poly.trait_ref.path.segments.last()?.ident.span);
+            }
+            hir::GenericBound::Outlives(_) => {}
+            // Other traits such as `DoubleEndedIterator` have no parallel counterpart.
+            _ => return None,
+        }
+    }
+    span
+}
+
+struct CallerFinder<'a, 'tcx> {
+    cx: &'a LateContext<'tcx>,
+    fn_def_id: DefId,
+    par_iter_methods: &'a [&'a str],
+    typeck: Option<&'tcx TypeckResults<'tcx>>,
+    calls: Vec<(HirId, Vec<Symbol>)>,
+    compatible: bool,
+    suggestions: Vec<(Span, String)>,
+}
+
+impl<'a, 'tcx> CallerFinder<'a, 'tcx> {
+    fn is_call_to_fn(&self, expr: &hir::Expr<'tcx>) -> bool {
+        self.typeck
+            .is_some_and(|typeck| callee(typeck, expr) == Some(self.fn_def_id))
+    }
+
+    /// Follows the methods called on the result of `call`, which must all exist on
+    /// `ParallelIterator` and end up in something which isn't an iterator anymore.
+    /// Returns the names of the parallel methods.
+    fn check_call(&mut self, call: &'tcx hir::Expr<'tcx>) -> Vec<Symbol> {
+        let mut methods = Vec::new();
+        let Some(typeck) = self.typeck else {
+            self.compatible = false;
+            return methods;
+        };
+        let mut top = call;
+        while let Some(parent) = get_parent_expr(self.cx, top)
+            && let hir::ExprKind::MethodCall(seg, recv, args, _) = parent.kind
+            && recv.hir_id == top.hir_id
+        {
+            let name = seg.ident.as_str();
+            if let Some((_, par_name)) = CALLER_RENAMES.iter().find(|(seq, _)| *seq == name) {
+                self.suggestions
+                    .push((seg.ident.span, (*par_name).to_string()));
+                methods.push(Symbol::intern(par_name));
+            } else if self.par_iter_methods.contains(&name) {
+                methods.push(seg.ident.name);
+            } else {
+                self.compatible = false;
+                return methods;
+            }
+            // Closures given to the chain are now shared between threads.
+            if args.iter().any(|arg| {
+                !is_type_valid(self.cx, typeck.expr_ty(arg)) || !is_closure_valid(self.cx, arg)
+            }) {
+                self.compatible = false;
+                return methods;
+            }
+            top = parent;
+        }
+
+        if top.hir_id == call.hir_id
+            || check_trait_impl(self.cx, typeck.expr_ty(top), sym::Iterator)
+        {
+            self.compatible = false;
+        }
+        methods
+    }
+}
+
+/// Checks that a closure given by a caller neither mutates nor shares the state it
+/// captures, nor touches global state. The caller's body isn't the one being linted,
+/// so this can't go through `Validator`, which reads the current typeck results.
+fn is_closure_valid<'tcx>(cx: &LateContext<'tcx>, arg: &'tcx hir::Expr<'tcx>) -> bool {
+    let hir::ExprKind::Closure(closure) = arg.kind else {
+        return true;
+    };
+    let body = cx.tcx.hir().body(closure.body);
+    let mut params = hir::HirIdSet::default();
+    for param in body.params {
+        if let hir::PatKind::Binding(_, hir_id, _, _) = param.pat.kind {
+            params.insert(hir_id);
+        }
+    }
+    // Thread locals are rejected too, as each thread would see its own value.
+    check_variables(cx, closure.def_id, body, &params, false)
+        && global_effects(cx, closure.def_id).is_empty()
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for CallerFinder<'a, 'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.cx.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: hir::BodyId) {
+        let old_typeck = self.typeck.replace(self.cx.tcx.typeck_body(body_id));
+        walk_body(self, self.cx.tcx.hir().body(body_id));
+        self.typeck = old_typeck;
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        if !self.compatible {
+            return;
+        }
+        if self.is_call_to_fn(expr) {
+            let methods = self.check_call(expr);
+            self.calls.push((expr.hir_id, methods));
+        }
+        walk_expr(self, expr);
+    }
+}
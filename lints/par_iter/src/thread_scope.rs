@@ -0,0 +1,197 @@
+use clippy_utils::get_parent_expr;
+use clippy_utils::source::snippet_indent;
+use clippy_utils::ty::get_iterator_item_ty;
+use rustc_hir as hir;
+use rustc_lint::{LateContext, LintContext};
+use rustc_middle::ty::{self, print::with_no_trimmed_paths};
+use serde::Deserialize;
+use utils::span_to_snippet_macro;
+use utils::variable_check::is_type_valid;
+
+/// What the suggestions parallelize the chains with.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Backend {
+    /// rayon's parallel iterators.
+    #[default]
+    Rayon,
+    /// `std::thread::scope`, for crates which can't depend on rayon.
+    ThreadScope,
+}
+
+/// The local holding the slice in the rewritten chain, which the function given to the
+/// chain must not capture a variable of the same name as.
+const ITEMS: &str = "items";
+
+/// A chain split between scoped threads.
+pub(crate) struct Scoped<'tcx> {
+    /// The end of the chain, which the code replaces.
+    pub(crate) top: &'tcx hir::Expr<'tcx>,
+    /// Whether the items are borrowed mutably.
+    pub(crate) is_mut: bool,
+    pub(crate) code: String,
+}
+
+/// Rewrites the chain from `source`, a call to `iter` or `iter_mut` on a slice, a `Vec`
+/// or an array, into a `std::thread::scope` running one chunk of the items per thread.
+/// Only `for_each(f)` and `map(f).collect()` are rewritten, the results of `map` being
+/// concatenated in the order of the chunks. Only `std`'s types are looked at, as the
+/// crate may not depend on rayon.
+pub(crate) fn rewrite<'tcx>(
+    cx: &LateContext<'tcx>,
+    source: &'tcx hir::Expr<'tcx>,
+) -> Option<Scoped<'tcx>> {
+    let hir::ExprKind::MethodCall(path, recv, [], _) = source.kind else {
+        return None;
+    };
+    let is_mut = match path.ident.as_str() {
+        "iter" => false,
+        "iter_mut" => true,
+        _ => return None,
+    };
+    // `Vec`s get here as slices, after auto-dereferencing.
+    let typeck = cx.typeck_results();
+    if !matches!(
+        typeck.expr_ty_adjusted(recv).peel_refs().kind(),
+        ty::Slice(_) | ty::Array(..)
+    ) || !is_type_valid(cx, typeck.expr_ty(recv))
+    {
+        return None;
+    }
+
+    let parent = get_parent_expr(cx, source)?;
+    let hir::ExprKind::MethodCall(adapter, _, [func], _) = parent.kind else {
+        return None;
+    };
+    // The chunks are collected like the items were, e.g. with the same turbofish.
+    let (top, collect) = match adapter.ident.as_str() {
+        "for_each" => (parent, None),
+        "map" => {
+            let top = get_parent_expr(cx, parent)?;
+            let hir::ExprKind::MethodCall(consumer, consumer_recv, [], _) = top.kind else {
+                return None;
+            };
+            if consumer.ident.as_str() != "collect" || consumer_recv.hir_id != parent.hir_id {
+                return None;
+            }
+            let collect = span_to_snippet_macro(
+                cx.sess().source_map(),
+                consumer.ident.span.with_hi(top.span.hi()),
+            );
+            (top, Some(collect))
+        }
+        _ => return None,
+    };
+
+    let item_ty = get_iterator_item_ty(cx, typeck.expr_ty(source))?;
+    let indent = snippet_indent(cx, top.span).unwrap_or_default();
+    // The function is bound in the block, one level deeper than the chain.
+    let func = reindent(
+        &shared_function(cx, func, item_ty)?,
+        &snippet_indent(cx, func.span).unwrap_or_default(),
+        &format!("{indent}    "),
+    );
+    let recv = span_to_snippet_macro(cx.sess().source_map(), recv.span);
+    let (items, chunks, iter) = if is_mut {
+        (
+            format!("&mut [_] = &mut {recv}[..]"),
+            "chunks_mut",
+            "iter_mut",
+        )
+    } else {
+        (format!("&[_] = &{recv}"), "chunks", "iter")
+    };
+    let head = format!(
+        "{{\n{indent}    let {ITEMS}: {items};\n\
+         {indent}    let f = &{func};\n\
+         {indent}    let threads = std::thread::available_parallelism().map_or(1, usize::from);\n\
+         {indent}    std::thread::scope(|scope| {{\n"
+    );
+    let chunks = format!("{ITEMS}.{chunks}({ITEMS}.len().div_ceil(threads).max(1))");
+    let body = match collect {
+        None => format!(
+            "{indent}        for chunk in {chunks} {{\n\
+             {indent}            scope.spawn(move || chunk.{iter}().for_each(f));\n\
+             {indent}        }}\n\
+             {indent}    }});\n"
+        ),
+        Some(collect) => format!(
+            "{indent}        let handles: Vec<_> = {chunks}\n\
+             {indent}            .map(|chunk| scope.spawn(move || chunk.{iter}().map(f).collect::<Vec<_>>()))\n\
+             {indent}            .collect();\n\
+             {indent}        handles\n\
+             {indent}            .into_iter()\n\
+             {indent}            .flat_map(|handle| handle.join().expect(\"a scoped thread panicked\"))\n\
+             {indent}            .{collect}\n\
+             {indent}    }})\n"
+        ),
+    };
+    Some(Scoped {
+        top,
+        is_mut,
+        code: format!("{head}{body}{indent}}}"),
+    })
+}
+
+/// Moves the lines of `snippet` after the first one from the indentation `from`, the
+/// one of the line it starts on, to `to`.
+fn reindent(snippet: &str, from: &str, to: &str) -> String {
+    snippet
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                line.to_string()
+            } else if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "{to}{}",
+                    line.strip_prefix(from).unwrap_or(line.trim_start())
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The function given to the chain, to be bound once and shared by reference between
+/// the threads. A closure gets the type of the items on its parameter, which it can't
+/// infer any more once it is bound outside of the chain.
+fn shared_function<'tcx>(
+    cx: &LateContext<'tcx>,
+    func: &'tcx hir::Expr<'tcx>,
+    item_ty: ty::Ty<'tcx>,
+) -> Option<String> {
+    let snippet = span_to_snippet_macro(cx.sess().source_map(), func.span);
+    match func.kind {
+        hir::ExprKind::Closure(closure) => {
+            let captures_items = cx
+                .tcx
+                .upvars_mentioned(closure.def_id)
+                .is_some_and(|upvars| {
+                    upvars
+                        .keys()
+                        .any(|&upvar| cx.tcx.hir().name(upvar).as_str() == ITEMS)
+                });
+            let [param] = cx.tcx.hir().body(closure.body).params else {
+                return None;
+            };
+            if captures_items || func.span.from_expansion() {
+                return None;
+            }
+            if !matches!(closure.fn_decl.inputs[0].kind, hir::TyKind::Infer) {
+                return Some(snippet);
+            }
+            let offset = (param.pat.span.hi() - func.span.lo()).to_usize();
+            let item_ty = with_no_trimmed_paths!(item_ty.to_string());
+            Some(format!(
+                "{}: {item_ty}{}",
+                snippet.get(..offset)?,
+                snippet.get(offset..)?
+            ))
+        }
+        hir::ExprKind::Path(_) if snippet != ITEMS => Some(snippet),
+        _ => None,
+    }
+}
@@ -0,0 +1,62 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+
+struct Countdown(u64);
+
+impl Iterator for Countdown {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.0 = self.0.checked_sub(1)?;
+        Some(self.0)
+    }
+}
+
+fn main() {}
+
+// should parallelize: a channel has no parallel iterator
+fn channel(rx: Receiver<u64>) -> Vec<String> {
+    rx.into_iter().map(|x| format!("{x}")).collect()
+}
+
+// should parallelize: the lines of a reader
+fn line_lengths(file: BufReader<File>) -> usize {
+    file.lines()
+        .map(|line| line.unwrap().to_uppercase().len())
+        .sum()
+}
+
+// should parallelize: an iterator of the crate
+fn sevens() -> usize {
+    Countdown(1000)
+        .map(|n| n.to_string())
+        .filter(|s| s.ends_with('7'))
+        .count()
+}
+
+// no: too little work for a bridge
+fn cheap_channel(rx: Receiver<u64>) -> u64 {
+    rx.into_iter().map(|x| x * 2).sum()
+}
+
+// no: the items can't be sent to other threads
+fn not_send(rx: Receiver<Rc<u64>>) -> usize {
+    rx.into_iter()
+        .map(|x| x.to_string())
+        .filter(|s| s.ends_with('7'))
+        .count()
+}
+
+// no: a bridge can't be indexed
+fn indexed(rx: Receiver<u64>) -> Vec<String> {
+    rx.into_iter()
+        .enumerate()
+        .map(|(i, x)| format!("{i}: {x}"))
+        .collect()
+}
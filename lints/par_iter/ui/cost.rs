@@ -0,0 +1,40 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+
+fn main() {}
+
+// no: three items
+fn short_range() {
+    (0..3).into_iter().for_each(|x| println!("{}", x));
+}
+
+// no: a single add per item
+fn single_add(numbers: &[i32]) -> Vec<i32> {
+    numbers.iter().map(|x| x + 1).collect()
+}
+
+// no: an expensive call, but on four items only
+fn short_array() {
+    let numbers = [1, 2, 3, 4];
+    let names: Vec<String> = numbers.iter().map(|x| x.to_string()).collect();
+}
+
+// should parallelize: an expensive call per item
+fn expensive_call(ids: &[u64]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+// should parallelize: a nested loop per item
+fn nested_loop(rows: &[Vec<u64>]) -> Vec<u64> {
+    rows.iter()
+        .map(|row| {
+            let mut total = 0;
+            for x in row {
+                total += x * x;
+            }
+            total
+        })
+        .collect()
+}
@@ -0,0 +1,49 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+fn main() {}
+
+// should use `par_extend`
+fn extend_squares(numbers: &[i32], squares: &mut Vec<i32>) {
+    squares.extend(numbers.iter().map(|x| x * x));
+}
+
+// should use `par_extend`
+fn extend_set(words: &[String], lengths: &mut HashSet<usize>) {
+    lengths.extend(words.iter().map(|word| word.len()));
+}
+
+// should use `par_extend` on what the `map` lints suggest
+fn extend_collected(numbers: &[i32], data: &mut Vec<i32>) {
+    data.extend(numbers.iter().map(|&num| num * 3).collect::<Vec<_>>());
+}
+
+// should collect into the cleared vector
+fn refill(numbers: &[i32], out: &mut Vec<i32>) {
+    out.clear();
+    out.extend(numbers.iter().map(|x| x + 1));
+}
+
+// should use `par_extend`, the iterator of `filter` is not indexed
+fn refill_evens(numbers: &[i32], evens: &mut Vec<i32>) {
+    evens.clear();
+    evens.extend(numbers.iter().filter(|x| *x % 2 == 0).copied());
+}
+
+struct Log(Vec<i32>);
+
+impl Extend<i32> for Log {
+    fn extend<I: IntoIterator<Item = i32>>(&mut self, iter: I) {
+        for x in iter {
+            self.0.push(x);
+        }
+    }
+}
+
+// no: `Log` doesn't implement `ParallelExtend`
+fn extend_log(numbers: &[i32], log: &mut Log) {
+    log.extend(numbers.iter().map(|x| x * 2));
+}
@@ -0,0 +1,52 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+use std::cell::Cell;
+
+fn main() {}
+
+static mut TOTAL: i32 = 0;
+
+// no: writes a `static mut`
+fn static_mut_write(numbers: &[i32]) {
+    numbers.iter().for_each(|_| unsafe { TOTAL += 1 });
+}
+
+fn add_to_total(x: i32) {
+    unsafe { TOTAL += x };
+}
+
+// no: a callee writes a `static mut`
+fn static_mut_write_in_callee(numbers: &[i32]) {
+    numbers.iter().for_each(|x| add_to_total(*x));
+}
+
+struct Counter(Cell<u32>);
+
+unsafe impl Sync for Counter {}
+
+static HITS: Counter = Counter(Cell::new(0));
+
+// no: the `Cell` is shared between threads without synchronization
+fn cell_static(numbers: &[i32]) {
+    numbers.iter().for_each(|_| HITS.0.set(HITS.0.get() + 1));
+}
+
+extern "C" {
+    fn rand() -> i32;
+}
+
+// no: calls a foreign function
+fn foreign_call(numbers: &[i32]) -> Vec<i32> {
+    numbers.iter().map(|_| unsafe { rand() }).collect()
+}
+
+thread_local! {
+    static SCALE: Cell<i32> = Cell::new(2);
+}
+
+// should parallelize, but each thread has its own `SCALE`
+fn thread_local_read(numbers: &[i32]) -> Vec<i32> {
+    numbers.iter().map(|x| x * SCALE.with(Cell::get)).collect()
+}
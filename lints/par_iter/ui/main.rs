@@ -489,3 +489,32 @@ fn return_loop() -> Option<()> {
     })?;
     Some(())
 }
+
+// should parallelize, a `HashSet` has no defined order anyway
+fn unordered_source_side_effects() {
+    let ids: HashSet<u32> = (0..10).collect();
+    ids.iter().for_each(|id| println!("{}", id));
+}
+
+// should parallelize, slices are indexed
+fn indexed_rev() {
+    let numbers = vec![1, 2, 3, 4];
+    let reversed: Vec<&i32> = numbers.iter().rev().collect();
+}
+
+// no: the iterator of a `HashSet` is not indexed
+fn unindexed_enumerate() {
+    let ids: HashSet<u32> = (0..10).collect();
+    let numbered: Vec<(usize, &u32)> = ids.iter().enumerate().collect();
+}
+
+// no: `filter` loses the indexing `rev` needs
+fn filtered_rev() {
+    let numbers = vec![1, 2, 3, 4];
+    let evens: Vec<&i32> = numbers.iter().filter(|x| *x % 2 == 0).rev().collect();
+}
+
+// should parallelize, with `flat_map_iter` for the sequential iterators of the closure
+fn repeated(counts: &[usize]) -> usize {
+    counts.iter().flat_map(|&n| std::iter::repeat(n).take(n)).count()
+}
@@ -0,0 +1,32 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+fn main() {}
+
+// should parallelize both sides
+fn zip_mut(a: &[i32], b: &mut [i32]) {
+    a.iter().zip(b.iter_mut()).for_each(|(x, y)| *y += x);
+}
+
+// should parallelize both sides
+fn chained(a: &[i32], b: &[i32]) -> i32 {
+    a.iter().chain(b.iter()).sum()
+}
+
+// should parallelize both sides
+fn same(a: &[i32], b: &[i32]) -> bool {
+    a.iter().eq(b.iter())
+}
+
+// should parallelize, rayon takes the slice as it is
+fn zip_slice(a: &[i32], b: &[i32]) -> i32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// no: the iterator of a `HashSet` is not indexed
+fn zip_set(a: &[i32], b: &HashSet<i32>) -> Vec<(i32, i32)> {
+    a.iter().zip(b.iter()).map(|(x, y)| (*x, *y)).collect()
+}
@@ -0,0 +1,36 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+
+fn main() {}
+
+// should parallelize the rows only
+fn matrix_sums(rows: &[Vec<u64>]) -> Vec<u64> {
+    rows.iter()
+        .map(|row| row.iter().map(|x| x * x).sum())
+        .collect()
+}
+
+// no: already runs in a parallel iterator
+fn already_parallel(rows: &[Vec<u64>]) -> Vec<u64> {
+    rows.par_iter()
+        .map(|row| row.iter().map(|x| x * x).sum())
+        .collect()
+}
+
+fn row_sum(row: &[u64]) -> u64 {
+    row.iter().map(|x| x * x).sum()
+}
+
+// no parallel iterator in `row_sum`: it is only called from a parallel iterator
+fn helper_sums(rows: &[Vec<u64>]) -> Vec<u64> {
+    rows.par_iter().map(|row| row_sum(row)).collect()
+}
+
+// should parallelize the rows and the iterators they are flattened from
+fn flattened_squares(rows: &[Vec<u64>]) -> Vec<u64> {
+    rows.iter()
+        .flat_map(|row| row.iter().map(|x| x * x))
+        .collect()
+}
@@ -0,0 +1,78 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use collections::{Bag, ParItems, ParShards, Shards};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn main() {}
+
+/// In-house collections, with parallel traits of their own.
+mod collections {
+    use rayon::prelude::*;
+
+    pub struct Bag(pub Vec<u64>);
+
+    impl Bag {
+        pub fn items(&self) -> std::slice::Iter<'_, u64> {
+            self.0.iter()
+        }
+    }
+
+    /// Hands out one of rayon's iterators.
+    pub trait ParItems {
+        fn par_items(&self) -> rayon::slice::Iter<'_, u64>;
+    }
+
+    impl ParItems for Bag {
+        fn par_items(&self) -> rayon::slice::Iter<'_, u64> {
+            self.0.par_iter()
+        }
+    }
+
+    pub struct Shards(pub Vec<Vec<u64>>);
+
+    impl Shards {
+        pub fn shards(&self) -> std::slice::Iter<'_, Vec<u64>> {
+            self.0.iter()
+        }
+    }
+
+    /// Hands out an iterator of its own, which only has `for_each`.
+    pub trait ParShards {
+        fn par_shards(&self) -> ShardIter<'_>;
+    }
+
+    impl ParShards for Shards {
+        fn par_shards(&self) -> ShardIter<'_> {
+            ShardIter(&self.0)
+        }
+    }
+
+    pub struct ShardIter<'a>(&'a [Vec<u64>]);
+
+    impl<'a> ShardIter<'a> {
+        pub fn for_each(self, f: impl Fn(&'a Vec<u64>) + Sync + Send) {
+            self.0.par_iter().for_each(f);
+        }
+    }
+}
+
+// should parallelize
+fn bag_sum(bag: &Bag) -> u64 {
+    bag.items().map(|x| x * 2).sum()
+}
+
+// should parallelize
+fn shard_sizes(shards: &Shards) -> usize {
+    let total = AtomicUsize::new(0);
+    shards.shards().for_each(|shard| {
+        total.fetch_add(shard.len(), Ordering::Relaxed);
+    });
+    total.into_inner()
+}
+
+// no: the shard iterator has no `map`
+fn shard_lens(shards: &Shards) -> usize {
+    shards.shards().map(|shard| shard.len()).sum()
+}
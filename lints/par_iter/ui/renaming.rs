@@ -0,0 +1,26 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+
+fn main() {}
+
+struct Cache {
+    iter_cache: Vec<u64>,
+}
+
+impl Cache {
+    // should rename the method, not the field holding the items
+    fn total(&self) -> u64 {
+        self.iter_cache.iter().map(|x| x * 2).sum()
+    }
+}
+
+// should rename each source, not the `iter` of the closure in its receiver
+fn totals(rows: &[Vec<u64>]) -> u64 {
+    rows.iter()
+        .map(|row| row.iter().sum::<u64>())
+        .collect::<Vec<_>>()
+        .iter()
+        .sum()
+}
@@ -0,0 +1,47 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+
+fn main() {}
+
+// should parallelize, together with its callers
+fn evens(numbers: &[i32]) -> impl Iterator<Item = i32> + '_ {
+    numbers.iter().copied().filter(|x| x % 2 == 0)
+}
+fn first_big_even(numbers: &[i32]) -> Option<i32> {
+    evens(numbers).find(|x| *x > 2)
+}
+
+fn sum_evens(numbers: &[i32]) -> i32 {
+    evens(numbers).sum()
+}
+
+// no: a caller uses `take`, which needs an indexed parallel iterator
+fn odds(numbers: &[i32]) -> impl Iterator<Item = i32> + '_ {
+    numbers.iter().copied().filter(|x| x % 2 == 1)
+}
+
+fn first_odds(numbers: &[i32]) -> Vec<i32> {
+    odds(numbers).take(2).collect()
+}
+
+// no: the function is part of the public API
+pub fn squares(numbers: &[i32]) -> impl Iterator<Item = i32> + '_ {
+    numbers.iter().map(|x| x * x)
+}
+
+fn sum_squares(numbers: &[i32]) -> i32 {
+    squares(numbers).sum()
+}
+
+// no: a caller's closure mutates a captured local
+fn multiples_of_three(numbers: &[i32]) -> impl Iterator<Item = i32> + '_ {
+    numbers.iter().copied().filter(|x| x % 3 == 0)
+}
+
+fn total_multiples_of_three(numbers: &[i32]) -> i32 {
+    let mut total = 0;
+    multiples_of_three(numbers).for_each(|v| total += v);
+    total
+}
@@ -0,0 +1,36 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+
+fn main() {}
+
+// should parallelize
+fn chunks() {
+    let data = vec![1, 2, 3, 4, 5, 6];
+    let lens: Vec<usize> = data.chunks(2).map(|c| c.len()).collect();
+}
+
+// should parallelize
+fn chars() {
+    let text = String::from("hello world");
+    let upper: String = text.chars().map(|c| c.to_ascii_uppercase()).collect();
+}
+
+// should parallelize
+fn split_char() {
+    let text = "a,b,c";
+    let parts: Vec<&str> = text.split(',').collect();
+}
+
+// no: rayon only splits on chars
+fn split_str() {
+    let text = "a, b, c";
+    let parts: Vec<&str> = text.split(", ").collect();
+}
+
+// should parallelize
+fn drain() {
+    let mut data = vec![1, 2, 3];
+    let drained: Vec<i32> = data.drain(..).collect();
+}
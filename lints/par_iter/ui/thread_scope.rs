@@ -0,0 +1,37 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn main() {}
+
+// should split the items between threads
+fn squares(data: &[u64]) -> Vec<u64> {
+    data.iter().map(|x| x * x).collect()
+}
+
+// should split the items between threads
+fn count_long(words: &[String]) -> usize {
+    let count = AtomicUsize::new(0);
+    words.iter().for_each(|word| {
+        if word.len() > 5 {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    count.into_inner()
+}
+
+// should split the items between threads
+fn double_all(values: &mut Vec<u64>) {
+    values.iter_mut().for_each(|x| *x *= 2);
+}
+
+// no: only chains over slices are split
+fn range_squares() -> Vec<u64> {
+    (0..100u64).into_iter().map(|x| x * x).collect()
+}
+
+// no: the chain goes on after `map`
+fn sum_squares(data: &[u64]) -> u64 {
+    data.iter().map(|x| x * x).sum()
+}
@@ -0,0 +1,162 @@
+use clippy_utils::visitors::for_each_expr_without_closures;
+use core::ops::ControlFlow;
+use rustc_errors::Applicability;
+use rustc_hir::{Block, Expr, ExprKind, HirId, LetStmt, MatchSource, PatKind, Stmt, StmtKind, Ty};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_session::{declare_lint, declare_lint_pass};
+use rustc_span::sym;
+use utils::cost::{work_cost_with_calls, DEFAULT_MIN_COST};
+use utils::global_effects::expr_global_effects;
+use utils::rayon_crate::skip_without_rayon;
+use utils::span_to_snippet_macro;
+use utils::variable_check::{check_trait_impl, used_variables, UsedVariables};
+
+use crate::par_divide_conquer::self_call;
+
+declare_lint! {
+    /// ### What it does
+    /// Suggests running two adjacent, independent and expensive computations at the
+    /// same time with `rayon::join`.
+    /// ### Why is this bad?
+    /// Computations which don't depend on each other can run on different threads.
+    /// ### Known problems
+    /// Input and output done by the computations, such as printing, is interleaved.
+    /// ### Example
+    /// ```rust
+    /// let a = expensive(x);
+    /// let b = expensive2(y);
+    /// ```
+    /// Use instead:
+    /// ```rust
+    /// let (a, b) = rayon::join(|| expensive(x), || expensive2(y));
+    /// ```
+    pub PAR_JOIN,
+    Warn,
+    "suggest running independent computations with rayon::join"
+}
+
+declare_lint_pass!(ParJoin => [PAR_JOIN]);
+
+impl<'tcx> LateLintPass<'tcx> for ParJoin {
+    fn check_block(&mut self, cx: &LateContext<'tcx>, block: &'tcx Block<'tcx>) {
+        let mut stmts = block.stmts;
+        while let [first, second, rest @ ..] = stmts {
+            // A statement joined with the one before it can't be joined again.
+            stmts = if let Some(a) = computation(cx, first)
+                && let Some(b) = computation(cx, second)
+                && are_independent(&a, &b)
+            {
+                emit(cx, first, second, &a, &b);
+                rest
+            } else {
+                &stmts[1..]
+            };
+        }
+    }
+}
+
+/// A `let` statement binding a variable to an expensive computation which can be moved
+/// to a closure.
+struct Computation<'tcx> {
+    local: &'tcx LetStmt<'tcx>,
+    init: &'tcx Expr<'tcx>,
+    binding: HirId,
+    used: UsedVariables<'tcx>,
+}
+
+fn computation<'tcx>(cx: &LateContext<'tcx>, stmt: &'tcx Stmt<'tcx>) -> Option<Computation<'tcx>> {
+    let StmtKind::Let(local) = stmt.kind else {
+        return None;
+    };
+    let PatKind::Binding(_, binding, _, None) = local.pat.kind else {
+        return None;
+    };
+    let init = local.init?;
+    // Recursive calls on the halves of a slice are left to `PAR_DIVIDE_CONQUER`, which
+    // adds a sequential cutoff.
+    if stmt.span.from_expansion()
+        || local.els.is_some()
+        || leaves_closure(init)
+        || self_call(cx, init).is_some()
+    {
+        return None;
+    }
+
+    // The result is sent back from the thread which computed it.
+    if !check_trait_impl(cx, cx.typeck_results().expr_ty(init), sym::Send)
+        || work_cost_with_calls(cx, init) < DEFAULT_MIN_COST
+        || !expr_global_effects(cx, init).is_empty()
+    {
+        return None;
+    }
+
+    // The closures capture the locals by reference, or move them.
+    let used = used_variables(cx, init);
+    if !used.can_run_on_other_thread(cx) {
+        return None;
+    }
+    Some(Computation {
+        local,
+        init,
+        binding,
+        used,
+    })
+}
+
+/// Checks whether `init` returns, breaks or uses `?`, which a closure would change the
+/// meaning of.
+fn leaves_closure(init: &Expr<'_>) -> bool {
+    for_each_expr_without_closures(init, |expr| match expr.kind {
+        ExprKind::Ret(_)
+        | ExprKind::Break(..)
+        | ExprKind::Continue(_)
+        | ExprKind::Yield(..)
+        | ExprKind::Match(_, _, MatchSource::TryDesugar(_)) => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    })
+    .is_some()
+}
+
+fn are_independent(a: &Computation<'_>, b: &Computation<'_>) -> bool {
+    !b.used.uses(a.binding) && a.used.is_independent_of(&b.used)
+}
+
+fn emit<'tcx>(
+    cx: &LateContext<'tcx>,
+    first: &'tcx Stmt<'tcx>,
+    second: &'tcx Stmt<'tcx>,
+    a: &Computation<'tcx>,
+    b: &Computation<'tcx>,
+) {
+    if skip_without_rayon(cx, PAR_JOIN, first.hir_id) {
+        return;
+    }
+    let source_map = cx.sess().source_map();
+    let pat_a = span_to_snippet_macro(source_map, a.local.pat.span);
+    let pat_b = span_to_snippet_macro(source_map, b.local.pat.span);
+    let init_a = span_to_snippet_macro(source_map, a.init.span);
+    let init_b = span_to_snippet_macro(source_map, b.init.span);
+    let ty = match (a.local.ty, b.local.ty) {
+        (None, None) => String::new(),
+        (ty_a, ty_b) => {
+            let ty_snippet = |ty: Option<&Ty<'_>>| {
+                ty.map_or_else(
+                    || "_".to_string(),
+                    |ty| span_to_snippet_macro(source_map, ty.span),
+                )
+            };
+            format!(": ({}, {})", ty_snippet(ty_a), ty_snippet(ty_b))
+        }
+    };
+
+    let span = first.span.to(second.span);
+    cx.span_lint(PAR_JOIN, span, |diag| {
+        diag.primary_message("independent computations run one after the other");
+        diag.span_suggestion(
+            span,
+            "try running them in parallel",
+            format!("let ({pat_a}, {pat_b}){ty} = rayon::join(|| {init_a}, || {init_b});"),
+            Applicability::MachineApplicable,
+        );
+    });
+}
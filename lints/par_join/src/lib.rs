@@ -0,0 +1,30 @@
+#![feature(rustc_private)]
+#![warn(unused_extern_crates)]
+#![feature(let_chains)]
+
+#[cfg(not(feature = "rlib"))]
+dylint_linting::dylint_library!();
+
+extern crate rustc_ast;
+#[cfg(feature = "rlib")]
+extern crate rustc_driver;
+extern crate rustc_errors;
+extern crate rustc_hir;
+extern crate rustc_lint;
+extern crate rustc_session;
+extern crate rustc_span;
+
+mod adjacent;
+mod par_divide_conquer;
+
+#[allow(clippy::no_mangle_with_rust_abi)]
+#[cfg_attr(not(feature = "rlib"), no_mangle)]
+pub fn register_lints(_sess: &rustc_session::Session, lint_store: &mut rustc_lint::LintStore) {
+    lint_store.register_late_pass(|_| Box::new(adjacent::ParJoin));
+    lint_store.register_late_pass(|_| Box::new(par_divide_conquer::ParDivideConquer));
+}
+
+#[test]
+fn ui() {
+    dylint_testing::ui_test_examples(env!("CARGO_PKG_NAME"));
+}
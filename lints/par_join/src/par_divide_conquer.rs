@@ -0,0 +1,313 @@
+use clippy_utils::higher::Range;
+use clippy_utils::source::snippet_indent;
+use clippy_utils::{path_to_local, SpanlessEq};
+use rustc_ast::ast::RangeLimits;
+use rustc_errors::Applicability;
+use rustc_hir::def::DefKind;
+use rustc_hir::{
+    BinOpKind, Block, BorrowKind, Expr, ExprKind, HirId, LetStmt, Mutability, Node, PatKind, Stmt,
+    StmtKind,
+};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_session::{declare_lint, declare_lint_pass};
+use rustc_span::{sym, Span};
+use utils::global_effects::expr_global_effects;
+use utils::rayon_crate::skip_without_rayon;
+use utils::span_to_snippet_macro;
+use utils::variable_check::{check_trait_impl, used_variables};
+
+/// Length under which the suggestion computes both halves on the current thread.
+const SEQUENTIAL_CUTOFF: usize = 1024;
+
+declare_lint! {
+    /// ### What it does
+    /// Suggests running the two recursive calls of a divide-and-conquer function on
+    /// the two halves of a slice at the same time with `rayon::join`, computing them
+    /// one after the other under a length threshold.
+    /// ### Why is this bad?
+    /// The halves don't overlap, so the calls can run on different threads.
+    /// ### Known problems
+    /// Only calls through the path of the function are recognized, not method calls.
+    /// The threshold of the suggestion is a guess, to be tuned to the work done per
+    /// element.
+    /// ### Example
+    /// ```rust
+    /// let (left, right) = v.split_at_mut(mid);
+    /// merge_sort(left);
+    /// merge_sort(right);
+    /// ```
+    /// Use instead:
+    /// ```rust
+    /// let (left, right) = v.split_at_mut(mid);
+    /// if left.len() + right.len() <= 1024 {
+    ///     merge_sort(left);
+    ///     merge_sort(right);
+    /// } else {
+    ///     rayon::join(|| merge_sort(left), || merge_sort(right));
+    /// }
+    /// ```
+    pub PAR_DIVIDE_CONQUER,
+    Warn,
+    "suggest running the recursive calls on the halves of a slice with rayon::join"
+}
+
+declare_lint_pass!(ParDivideConquer => [PAR_DIVIDE_CONQUER]);
+
+impl<'tcx> LateLintPass<'tcx> for ParDivideConquer {
+    fn check_block(&mut self, cx: &LateContext<'tcx>, block: &'tcx Block<'tcx>) {
+        for pair in block.stmts.windows(2) {
+            let [first, second] = pair else {
+                continue;
+            };
+            if let Some(calls) = stmt_calls(first, second)
+                && let Some(len) = halves_len(cx, calls)
+            {
+                emit_stmts(cx, first, second, calls, &len);
+                // The pair is the only one a function splits its slice into.
+                return;
+            }
+        }
+    }
+
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
+        // The `if` replacing the operation can't be an operand.
+        if let ExprKind::Binary(op, a, b) = expr.kind
+            && !matches!(op.node, BinOpKind::And | BinOpKind::Or)
+            && matches!(
+                cx.tcx.parent_hir_node(expr.hir_id),
+                Node::Block(_) | Node::LetStmt(_)
+            )
+            && let Some(len) = halves_len(cx, (a, b))
+        {
+            emit_binary(cx, expr, op.node, (a, b), &len);
+        }
+    }
+}
+
+/// Returns the call if `expr` calls the function its body belongs to.
+pub(crate) fn self_call<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx Expr<'tcx>,
+) -> Option<&'tcx [Expr<'tcx>]> {
+    let ExprKind::Call(func, args) = expr.kind else {
+        return None;
+    };
+    let ExprKind::Path(ref qpath) = func.kind else {
+        return None;
+    };
+    let owner = expr.hir_id.owner.to_def_id();
+    if !matches!(cx.tcx.def_kind(owner), DefKind::Fn | DefKind::AssocFn)
+        || cx
+            .typeck_results()
+            .qpath_res(qpath, func.hir_id)
+            .opt_def_id()
+            != Some(owner)
+    {
+        return None;
+    }
+    Some(args)
+}
+
+/// The two calls of a pair of statements, both binding their result to a new local or
+/// both discarding it.
+fn stmt_calls<'tcx>(
+    first: &'tcx Stmt<'tcx>,
+    second: &'tcx Stmt<'tcx>,
+) -> Option<(&'tcx Expr<'tcx>, &'tcx Expr<'tcx>)> {
+    match (first.kind, second.kind) {
+        (StmtKind::Let(a), StmtKind::Let(b)) => Some((simple_init(a)?, simple_init(b)?)),
+        (StmtKind::Semi(a), StmtKind::Semi(b)) => Some((a, b)),
+        _ => None,
+    }
+}
+
+fn simple_init<'tcx>(local: &'tcx LetStmt<'tcx>) -> Option<&'tcx Expr<'tcx>> {
+    match local.pat.kind {
+        PatKind::Binding(_, _, _, None) if local.ty.is_none() && local.els.is_none() => local.init,
+        _ => None,
+    }
+}
+
+/// Checks that `a` and `b` are two calls of the function to itself, which can run at
+/// the same time since they only differ by the halves of a slice they are given.
+/// Returns the length of that slice.
+fn halves_len<'tcx>(
+    cx: &LateContext<'tcx>,
+    (a, b): (&'tcx Expr<'tcx>, &'tcx Expr<'tcx>),
+) -> Option<String> {
+    if a.span.from_expansion() || b.span.from_expansion() {
+        return None;
+    }
+    let args_a = self_call(cx, a)?;
+    let args_b = self_call(cx, b)?;
+    let (len, split) = args_a
+        .iter()
+        .zip(args_b)
+        .find_map(|(arg_a, arg_b)| split_slice(cx, arg_a, arg_b))?;
+
+    // The results are sent back from the threads which computed them.
+    let typeck = cx.typeck_results();
+    if !check_trait_impl(cx, typeck.expr_ty(a), sym::Send)
+        || !expr_global_effects(cx, a).is_empty()
+        || !expr_global_effects(cx, b).is_empty()
+    {
+        return None;
+    }
+
+    // The halves of the slice are the only locals both calls may write.
+    let mut used_a = used_variables(cx, a);
+    let mut used_b = used_variables(cx, b);
+    if let Some(split) = split {
+        for used in [&mut used_a, &mut used_b] {
+            used.read.remove(&split);
+            used.written.remove(&split);
+        }
+    }
+    if !used_a.is_independent_of(&used_b)
+        || !used_a.can_run_on_other_thread(cx)
+        || !used_b.can_run_on_other_thread(cx)
+    {
+        return None;
+    }
+    Some(len)
+}
+
+/// Checks that `a` and `b` are the two halves of a slice: `&s[..mid]` and `&s[mid..]`,
+/// or the two locals bound by `split_at(mid)` or `split_at_mut(mid)`. Returns the length
+/// of the slice and, for the first form, the local which is split. `&mut s[..mid]` and
+/// `&mut s[mid..]` would borrow `s` mutably twice in the closures given to the join.
+fn split_slice<'tcx>(
+    cx: &LateContext<'tcx>,
+    a: &'tcx Expr<'tcx>,
+    b: &'tcx Expr<'tcx>,
+) -> Option<(String, Option<HirId>)> {
+    let source_map = cx.sess().source_map();
+    if let ExprKind::AddrOf(BorrowKind::Ref, mutbl_a, indexed_a) = a.kind
+        && let ExprKind::AddrOf(BorrowKind::Ref, mutbl_b, indexed_b) = b.kind
+        && mutbl_a == Mutability::Not
+        && mutbl_b == Mutability::Not
+        && let ExprKind::Index(base_a, range_a, _) = indexed_a.kind
+        && let ExprKind::Index(base_b, range_b, _) = indexed_b.kind
+        && let Some(range_a) = Range::hir(range_a)
+        && let Some(range_b) = Range::hir(range_b)
+        && range_a.limits == RangeLimits::HalfOpen
+        && range_b.limits == RangeLimits::HalfOpen
+        && let (None, Some(mid_a), Some(mid_b), None) =
+            (range_a.start, range_a.end, range_b.start, range_b.end)
+        && SpanlessEq::new(cx)
+            .deny_side_effects()
+            .eq_expr(base_a, base_b)
+        && SpanlessEq::new(cx)
+            .deny_side_effects()
+            .eq_expr(mid_a, mid_b)
+    {
+        let base = span_to_snippet_macro(source_map, base_a.span);
+        return Some((format!("{base}.len()"), path_to_local(base_a)));
+    }
+
+    let left = path_to_local(a)?;
+    let right = path_to_local(b)?;
+    let (Node::Pat(pat_a), Node::Pat(pat_b)) =
+        (cx.tcx.parent_hir_node(left), cx.tcx.parent_hir_node(right))
+    else {
+        return None;
+    };
+    let PatKind::Tuple([first, second], _) = pat_a.kind else {
+        return None;
+    };
+    if pat_a.hir_id != pat_b.hir_id || first.hir_id != left || second.hir_id != right {
+        return None;
+    }
+    let Node::LetStmt(LetStmt {
+        init: Some(init), ..
+    }) = cx.tcx.parent_hir_node(pat_a.hir_id)
+    else {
+        return None;
+    };
+    let ExprKind::MethodCall(path, _, [_], _) = init.kind else {
+        return None;
+    };
+    if !["split_at", "split_at_mut"].contains(&path.ident.as_str()) {
+        return None;
+    }
+    let left = span_to_snippet_macro(source_map, a.span);
+    let right = span_to_snippet_macro(source_map, b.span);
+    Some((format!("{left}.len() + {right}.len()"), None))
+}
+
+fn emit_stmts<'tcx>(
+    cx: &LateContext<'tcx>,
+    first: &'tcx Stmt<'tcx>,
+    second: &'tcx Stmt<'tcx>,
+    (a, b): (&'tcx Expr<'tcx>, &'tcx Expr<'tcx>),
+    len: &str,
+) {
+    let source_map = cx.sess().source_map();
+    let call_a = span_to_snippet_macro(source_map, a.span);
+    let call_b = span_to_snippet_macro(source_map, b.span);
+    let indent = snippet_indent(cx, first.span).unwrap_or_default();
+    let suggestion = match (first.kind, second.kind) {
+        (StmtKind::Let(local_a), StmtKind::Let(local_b)) => {
+            let pat_a = span_to_snippet_macro(source_map, local_a.pat.span);
+            let pat_b = span_to_snippet_macro(source_map, local_b.pat.span);
+            format!(
+                "let ({pat_a}, {pat_b}) = if {len} <= {SEQUENTIAL_CUTOFF} {{\n\
+                 {indent}    ({call_a}, {call_b})\n\
+                 {indent}}} else {{\n\
+                 {indent}    rayon::join(|| {call_a}, || {call_b})\n\
+                 {indent}}};"
+            )
+        }
+        _ => format!(
+            "if {len} <= {SEQUENTIAL_CUTOFF} {{\n\
+             {indent}    {call_a};\n\
+             {indent}    {call_b};\n\
+             {indent}}} else {{\n\
+             {indent}    rayon::join(|| {call_a}, || {call_b});\n\
+             {indent}}}"
+        ),
+    };
+    emit(cx, first.hir_id, first.span.to(second.span), suggestion);
+}
+
+fn emit_binary<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx Expr<'tcx>,
+    op: BinOpKind,
+    (a, b): (&'tcx Expr<'tcx>, &'tcx Expr<'tcx>),
+    len: &str,
+) {
+    let source_map = cx.sess().source_map();
+    let call_a = span_to_snippet_macro(source_map, a.span);
+    let call_b = span_to_snippet_macro(source_map, b.span);
+    let op = op.as_str();
+    let indent = snippet_indent(cx, expr.span).unwrap_or_default();
+    let suggestion = format!(
+        "if {len} <= {SEQUENTIAL_CUTOFF} {{\n\
+         {indent}    {call_a} {op} {call_b}\n\
+         {indent}}} else {{\n\
+         {indent}    let (left, right) = rayon::join(|| {call_a}, || {call_b});\n\
+         {indent}    left {op} right\n\
+         {indent}}}"
+    );
+    emit(cx, expr.hir_id, expr.span, suggestion);
+}
+
+fn emit(cx: &LateContext<'_>, hir_id: HirId, span: Span, suggestion: String) {
+    if skip_without_rayon(cx, PAR_DIVIDE_CONQUER, hir_id) {
+        return;
+    }
+    cx.span_lint(PAR_DIVIDE_CONQUER, span, |diag| {
+        diag.primary_message("recursive calls on the halves of a slice run one after the other");
+        diag.note(format!(
+            "up to {SEQUENTIAL_CUTOFF} elements, the halves are still computed one after \
+             the other, since sending them to other threads would cost more than it saves"
+        ));
+        diag.span_suggestion(
+            span,
+            "try running them in parallel",
+            suggestion,
+            Applicability::MachineApplicable,
+        );
+    });
+}
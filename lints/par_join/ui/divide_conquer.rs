@@ -0,0 +1,87 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+fn main() {}
+
+// should join the halves
+fn sum(s: &[u64]) -> u64 {
+    if s.len() <= 1 {
+        return s.iter().sum();
+    }
+    let mid = s.len() / 2;
+    sum(&s[..mid]) + sum(&s[mid..])
+}
+
+// should join the halves
+fn max(s: &[u64]) -> u64 {
+    if s.len() <= 1 {
+        return s.first().copied().unwrap_or_default();
+    }
+    let mid = s.len() / 2;
+    let left = max(&s[..mid]);
+    let right = max(&s[mid..]);
+    left.max(right)
+}
+
+// should join the halves
+fn merge_sort(v: &mut [u64]) {
+    if v.len() <= 1 {
+        return;
+    }
+    let mid = v.len() / 2;
+    let (left, right) = v.split_at_mut(mid);
+    merge_sort(left);
+    merge_sort(right);
+    v.sort_unstable();
+}
+
+// no: the halves overlap
+fn overlapping(s: &[u64]) -> u64 {
+    if s.len() <= 2 {
+        return s.iter().sum();
+    }
+    let mid = s.len() / 2;
+    overlapping(&s[..mid + 1]) + overlapping(&s[mid..])
+}
+
+// no: both calls write the same counter
+fn count(s: &[u64], calls: &mut usize) {
+    *calls += 1;
+    if s.len() <= 1 {
+        return;
+    }
+    let mid = s.len() / 2;
+    count(&s[..mid], calls);
+    count(&s[mid..], calls);
+}
+
+// no: `||` doesn't evaluate its right side when its left side is true
+fn contains(s: &[u64], x: u64) -> bool {
+    if s.len() <= 1 {
+        return s.first() == Some(&x);
+    }
+    let mid = s.len() / 2;
+    contains(&s[..mid], x) || contains(&s[mid..], x)
+}
+
+// no: the halves would borrow the slice mutably twice
+fn double(s: &mut [u64]) {
+    if s.len() <= 1 {
+        s.iter_mut().for_each(|x| *x *= 2);
+        return;
+    }
+    let mid = s.len() / 2;
+    double(&mut s[..mid]);
+    double(&mut s[mid..]);
+}
+
+// no: the `Cell` behind the reference can't be shared between threads
+fn count_cells(s: &[u64], calls: &std::cell::Cell<usize>) {
+    calls.set(calls.get() + 1);
+    if s.len() <= 1 {
+        return;
+    }
+    let mid = s.len() / 2;
+    count_cells(&s[..mid], calls);
+    count_cells(&s[mid..], calls);
+}
@@ -0,0 +1,97 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use std::rc::Rc;
+
+fn main() {}
+
+fn sum_squares(n: u64) -> u64 {
+    let mut total = 0;
+    for i in 0..100_000 {
+        total += i * i % n;
+    }
+    total
+}
+
+fn sum_cubes(n: u64) -> u64 {
+    let mut total = 0;
+    for i in 0..100_000 {
+        total += i * i * i % n;
+    }
+    total
+}
+
+fn fill_squares(squares: &mut Vec<u64>) -> usize {
+    for i in 0..100_000 {
+        squares.push(i * i);
+    }
+    squares.len()
+}
+
+// should run both at once
+fn independent(x: u64, y: u64) -> u64 {
+    let a = sum_squares(x);
+    let b = sum_cubes(y);
+    a + b
+}
+
+// should run both at once
+fn annotated(x: u64) -> u64 {
+    let mut a: u64 = sum_squares(x);
+    let b = sum_cubes(x);
+    a += b;
+    a
+}
+
+// no: the second computation uses the first
+fn dependent(x: u64) -> u64 {
+    let a = sum_squares(x);
+    let b = sum_cubes(a);
+    a + b
+}
+
+// no: both computations write to the same vector
+fn shared_vec(squares: &mut Vec<u64>) -> usize {
+    let a = fill_squares(squares);
+    let b = fill_squares(squares);
+    a + b
+}
+
+// no: the second computation is cheap
+fn cheap(x: u64) -> u64 {
+    let a = sum_squares(x);
+    let b = x + 1;
+    a + b
+}
+
+// no: an `Rc` can't be sent to another thread
+fn not_send(x: u64) -> u64 {
+    let a = Rc::new(sum_squares(x));
+    let b = Rc::new(sum_cubes(x));
+    *a + *b
+}
+
+fn counted_squares(calls: &std::cell::Cell<u64>, n: u64) -> u64 {
+    calls.set(calls.get() + 1);
+    sum_squares(n)
+}
+
+// no: the `Cell` behind the reference can't be shared between threads
+fn not_sync(calls: &std::cell::Cell<u64>, x: u64) -> u64 {
+    let a = counted_squares(calls, x);
+    let b = sum_cubes(x);
+    a + b
+}
+
+fn logged_squares(log: &std::sync::Mutex<Vec<u64>>, n: u64) -> u64 {
+    let total = sum_squares(n);
+    log.lock().unwrap().push(total);
+    total
+}
+
+// no: both computations write to the vector behind the mutex
+fn shared_mutex(log: &std::sync::Mutex<Vec<u64>>, x: u64, y: u64) -> u64 {
+    let a = logged_squares(log, x);
+    let b = logged_squares(log, y);
+    a + b
+}
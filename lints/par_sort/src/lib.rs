@@ -0,0 +1,123 @@
+#![feature(rustc_private)]
+#![warn(unused_extern_crates)]
+#![feature(let_chains)]
+
+extern crate rustc_errors;
+extern crate rustc_hir;
+extern crate rustc_middle;
+extern crate rustc_span;
+
+use clippy_utils::get_trait_def_id;
+use rustc_errors::Applicability;
+use rustc_hir::intravisit::Visitor;
+use rustc_hir::{Expr, ExprKind};
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_middle::ty::TyKind;
+use rustc_span::sym;
+use utils::cost::known_len;
+use utils::par_modules::{record_par_suggestion, ParUse};
+use utils::par_sources::implements_rayon_trait;
+use utils::rayon_crate::{crate_has_rayon, skip_without_rayon};
+use utils::validator::Validator;
+use utils::variable_check::check_trait_impl;
+
+dylint_linting::declare_late_lint! {
+    /// ### What it does
+    /// Suggests sorting slices with rayon's parallel sorts.
+    /// ### Why is this bad?
+    /// Sorting large slices is CPU-bound and splits well between threads.
+    /// ### Known problems
+    /// Sorting a small slice in parallel is slower than sorting it sequentially.
+    /// ### Example
+    /// ```rust
+    /// people.sort_by_key(|p| p.age);
+    /// ```
+    /// Use instead:
+    /// ```rust
+    /// use rayon::slice::ParallelSliceMut;
+    ///
+    /// people.par_sort_by_key(|p| p.age);
+    /// ```
+    pub PAR_SORT,
+    Warn,
+    "suggest using parallel sorts"
+}
+
+/// Sequential sorts of `[T]` and their counterparts in `ParallelSliceMut`.
+const SORTS: &[(&str, &str)] = &[
+    ("sort", "par_sort"),
+    ("sort_by", "par_sort_by"),
+    ("sort_by_key", "par_sort_by_key"),
+    ("sort_unstable", "par_sort_unstable"),
+    ("sort_unstable_by", "par_sort_unstable_by"),
+    ("sort_unstable_by_key", "par_sort_unstable_by_key"),
+];
+
+/// Slices with fewer elements than this are sorted faster sequentially.
+const SMALL_LEN: u64 = 1024;
+
+impl<'tcx> LateLintPass<'tcx> for ParSort {
+    fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(path, recv, _args, _span) = expr.kind
+            && let Some(&(_, par_name)) =
+                SORTS.iter().find(|(name, _)| path.ident.as_str() == *name)
+        {
+            // The sort must be the one of `[T]`, reached through auto-dereferencing.
+            let slice_ty = cx.typeck_results().expr_ty_adjusted(recv).peel_refs();
+            let TyKind::Slice(elem_ty) = slice_ty.kind() else {
+                return;
+            };
+            // Without rayon, its traits aren't loaded and the sort is only counted.
+            let par_slice_mut = get_trait_def_id(cx.tcx, &["rayon", "slice", "ParallelSliceMut"]);
+            let is_par_slice = match par_slice_mut {
+                Some(par_slice_mut) => implements_rayon_trait(cx, slice_ty, par_slice_mut),
+                None => !crate_has_rayon(cx),
+            };
+            if !is_par_slice || !check_trait_impl(cx, *elem_ty, sym::Send) {
+                return;
+            }
+
+            if is_clearly_small(cx, recv) {
+                return;
+            }
+
+            // Comparators and key functions are called from several threads, where a
+            // thread local would give inconsistent orderings.
+            let mut validator = Validator::new(cx, false);
+            validator.visit_expr(expr);
+            if !validator.is_valid || validator.thread_local.is_some() {
+                return;
+            }
+
+            if skip_without_rayon(cx, PAR_SORT, expr.hir_id) {
+                return;
+            }
+            let par_use = ParUse {
+                hir_id: expr.hir_id,
+                traits: par_slice_mut.into_iter().collect(),
+            };
+            record_par_suggestion(cx, PAR_SORT, expr.hir_id, &[par_use]);
+
+            cx.span_lint(PAR_SORT, expr.span, |diag| {
+                diag.primary_message("sequential sort");
+                diag.span_suggestion_verbose(
+                    path.ident.span,
+                    "try using a parallel sort",
+                    par_name,
+                    Applicability::MachineApplicable,
+                );
+            });
+        }
+    }
+}
+
+/// Checks whether the sorted value is an array, or a local initialized by `vec![...]`
+/// and not changed since, with few elements.
+fn is_clearly_small<'tcx>(cx: &LateContext<'tcx>, recv: &'tcx Expr<'tcx>) -> bool {
+    known_len(cx, recv).is_some_and(|len| len < SMALL_LEN)
+}
+
+#[test]
+fn ui() {
+    dylint_testing::ui_test_examples(env!("CARGO_PKG_NAME"));
+}
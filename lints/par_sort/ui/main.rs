@@ -0,0 +1,60 @@
+// run-rustfix
+#![allow(dead_code, unused_imports, unused_variables)]
+
+use rayon::prelude::*;
+use std::rc::Rc;
+
+struct Person {
+    name: String,
+    age: u32,
+}
+
+fn main() {}
+
+// should parallelize
+fn sort_numbers(numbers: &mut Vec<u64>) {
+    numbers.sort();
+}
+
+// should parallelize
+fn sort_people(people: &mut [Person]) {
+    people.sort_by_key(|p| p.age);
+}
+
+// should parallelize
+fn sort_words(words: &mut Vec<String>) {
+    words.sort_unstable_by(|a, b| b.len().cmp(&a.len()));
+}
+
+// no: the vector is clearly small
+fn small_vec() {
+    let mut numbers = vec![3, 1, 2];
+    numbers.sort();
+}
+
+// no: the array is clearly small
+fn small_array() {
+    let mut numbers = [3, 1, 2];
+    numbers.sort_unstable();
+}
+
+// no: `Rc` is not `Send`
+fn not_send(values: &mut Vec<Rc<u32>>) {
+    values.sort();
+}
+
+// no: the comparator mutates a capture
+fn counting_comparator(numbers: &mut Vec<u64>) {
+    let mut comparisons = 0;
+    numbers.sort_by(|a, b| {
+        comparisons += 1;
+        a.cmp(b)
+    });
+}
+
+// should parallelize: the vector was empty, but has grown since
+fn grown_vec(more: &[u64]) {
+    let mut numbers = vec![];
+    numbers.extend(more);
+    numbers.sort();
+}
@@ -4,6 +4,7 @@
 #[cfg(not(feature = "rlib"))]
 dylint_linting::dylint_library!();
 
+extern crate rustc_data_structures;
 #[cfg(feature = "rlib")]
 extern crate rustc_driver;
 extern crate rustc_errors;
@@ -11,14 +12,15 @@ extern crate rustc_hir;
 extern crate rustc_lint;
 extern crate rustc_middle;
 extern crate rustc_session;
-extern crate rustc_span;
 
+mod rayon_dependency;
 mod rayon_prelude;
 
 #[allow(clippy::no_mangle_with_rust_abi)]
 #[cfg_attr(not(feature = "rlib"), no_mangle)]
 pub fn register_lints(_sess: &rustc_session::Session, lint_store: &mut rustc_lint::LintStore) {
     lint_store.register_late_pass(|_| Box::new(rayon_prelude::RayonPrelude));
+    lint_store.register_late_pass(|_| Box::new(rayon_dependency::RayonDependency));
 }
 
 #[test]
@@ -0,0 +1,67 @@
+use clippy_utils::is_no_std_crate;
+use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_session::{declare_lint, declare_lint_pass};
+use utils::rayon_crate::take_skipped;
+
+declare_lint! {
+    /// ### What it does
+    /// Counts the parallelization opportunities the other lints skipped because the crate
+    /// can't use rayon: it doesn't depend on rayon, or it is `#![no_std]`.
+    ///
+    /// ### Why is this bad?
+    /// Adding rayon to the dependencies turns the opportunities into suggestions.
+    ///
+    /// ### Known problems
+    /// The opportunities are counted by the lints registered before this one in the same
+    /// library, so it reports nothing when loaded on its own. rustc loads rayon only when
+    /// the crate names it, so without rayon the sources of parallel iterators are found by
+    /// their `std` types instead. Only the chains ending in a reduction, or in a `for_each`
+    /// updating a counter, are counted that way: the other chains `par_iter` suggests are
+    /// missing from the count.
+    ///
+    /// ### Example
+    /// ```toml
+    /// [dependencies]
+    /// ```
+    /// Use instead:
+    /// ```toml
+    /// [dependencies]
+    /// rayon = "1.9.0"
+    /// ```
+
+    pub RAYON_DEPENDENCY,
+    Allow,
+    "count the parallelization opportunities skipped in crates without rayon"
+}
+
+declare_lint_pass!(RayonDependency => [RAYON_DEPENDENCY]);
+
+impl LateLintPass<'_> for RayonDependency {
+    fn check_crate_post(&mut self, cx: &LateContext<'_>) {
+        let skipped = take_skipped();
+        if skipped == 0 {
+            return;
+        }
+        let opportunities = if skipped == 1 {
+            "1 parallelization opportunity".to_string()
+        } else {
+            format!("{skipped} parallelization opportunities")
+        };
+        let no_std = is_no_std_crate(cx);
+        cx.lint(RAYON_DEPENDENCY, |diag| {
+            if no_std {
+                diag.primary_message(format!("{opportunities} skipped in a `no_std` crate"));
+                diag.note("rayon needs `std`");
+            } else {
+                diag.primary_message(format!(
+                    "{opportunities} skipped, since the crate doesn't depend on rayon"
+                ));
+                diag.help("add rayon to the dependencies of the crate with `cargo add rayon`");
+            }
+            diag.note(
+                "iterator chains are only counted if they end in a reduction or in a \
+                 `for_each` updating a counter",
+            );
+        });
+    }
+}
@@ -1,23 +1,37 @@
-use rustc_hir::{intravisit::Visitor, Item, ItemKind, UseKind};
+use clippy_utils::def_path_def_ids;
+use clippy_utils::source::snippet_indent;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
 use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_middle::metadata::ModChild;
+use rustc_middle::ty::print::with_no_trimmed_paths;
 use rustc_session::{declare_lint, declare_lint_pass};
-use rustc_span::{FileName, FileNameDisplayPreference};
+use utils::par_modules::take_par_modules;
+use utils::rayon_crate::crate_has_rayon;
 
 declare_lint! {
     /// ### What it does
-    /// Checks the rayon iterator trait are import.
+    /// Checks that the rayon traits whose methods the other lints suggest calling are in
+    /// scope in the modules of the suggestions. Suggests importing the missing traits, or
+    /// `rayon::prelude::*` if several of its traits are missing.
     ///
     /// ### Why is this bad?
-    /// Not importing can lead to missed opportunities for
-    /// parallelization and performance optimization when using Rayon.
+    /// Without the import, the suggested parallel code doesn't compile.
     ///
     /// ### Known problems
-    /// This lint does not check if Rayon is actually used, so it might suggest
-    /// imports that are unnecessary. but can be fixed using cargo fix.
+    /// The modules are recorded by the lints registered before this one in the same
+    /// library, so it reports nothing when loaded on its own. Traits imported in the
+    /// block of a function are not seen.
     ///
     /// ### Example
     /// ```
-    /// use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    /// use rayon::iter::ParallelIterator;
+    /// ```
+    /// Use instead, when `iter` is turned into `par_iter`:
+    /// ```
+    /// use rayon::iter::IntoParallelRefIterator;
+    /// use rayon::iter::ParallelIterator;
     /// ```
 
     pub RAYON_PRELUDE,
@@ -28,68 +42,78 @@ declare_lint! {
 declare_lint_pass!(RayonPrelude => [RAYON_PRELUDE]);
 
 impl LateLintPass<'_> for RayonPrelude {
-    fn check_mod(
-        &mut self,
-        cx: &LateContext<'_>,
-        md: &'_ rustc_hir::Mod<'_>,
-        _hir_id: rustc_hir::HirId,
-    ) {
-        // Skip linting if in build.rs file
-        if let FileName::Real(f) = cx
-            .sess()
-            .source_map()
-            .span_to_filename(cx.tcx.hir().root_module().spans.inner_span)
-        {
-            if f.to_string_lossy(FileNameDisplayPreference::Short)
-                .ends_with("build.rs")
-            {
-                return;
-            }
+    fn check_crate_post(&mut self, cx: &LateContext<'_>) {
+        // The parallelizing lints run before, and record their modules up to the end of
+        // the crate.
+        let modules = take_par_modules();
+        if !crate_has_rayon(cx) {
+            return;
         }
 
+        let prelude: FxHashSet<DefId> = def_path_def_ids(cx.tcx, &["rayon", "prelude"])
+            .flat_map(|prelude| traits(cx.tcx.module_children(prelude)))
+            .collect();
         let hir = cx.tcx.hir();
-        let mut use_statement_visitor = UseStatementVisitor { has_import: false };
-        let module_item_ids = md.item_ids;
-        for item_id in module_item_ids {
-            let item = hir.item(*item_id);
-            use_statement_visitor.visit_item(item);
-        }
+        for par_module in modules {
+            // Whatever form of `use` brings a trait in, by a glob, under another name or
+            // through a re-export, it ends up among the names of the module.
+            let children = cx
+                .tcx
+                .module_children_local(par_module.module.to_local_def_id());
+            let in_scope: FxHashSet<DefId> = traits(children).collect();
+            let (from_prelude, others): (Vec<DefId>, Vec<DefId>) = par_module
+                .traits
+                .into_iter()
+                .filter(|def_id| !in_scope.contains(def_id))
+                .partition(|def_id| prelude.contains(def_id));
+
+            let mut imports = Vec::new();
+            if from_prelude.len() > 1 {
+                imports.push("rayon::prelude::*".to_string());
+            } else {
+                imports.extend(from_prelude.iter().map(|&def_id| trait_path(cx, def_id)));
+            }
+            imports.extend(others.iter().map(|&def_id| trait_path(cx, def_id)));
+            if imports.is_empty() {
+                continue;
+            }
 
-        // @todo remove #[allow(unused_imports)] and only add the import when it is going to be used
-        let import_suggestion = "#[allow(unused_imports)]\nuse rayon::prelude::*;\n".to_string();
-        let inject_use_span = md.spans.inject_use_span;
-        if !use_statement_visitor.has_import {
-            cx.span_lint(RAYON_PRELUDE, inject_use_span, |diag| {
-                diag.primary_message("rayon::prelude::* is not imported");
-                diag.span_suggestion(
-                    inject_use_span,
-                    "consider adding this import",
-                    import_suggestion,
-                    rustc_errors::Applicability::MachineApplicable,
-                );
-            });
+            // The first item of an inline module is indented like the import.
+            let (md, _, hir_id) = hir.get_module(par_module.module);
+            let inject_use_span = md.spans.inject_use_span;
+            let indent = snippet_indent(cx, inject_use_span).unwrap_or_default();
+            let import_suggestion: String = imports
+                .iter()
+                .map(|path| format!("use {path};\n{indent}"))
+                .collect();
+            let (verb, help) = if imports.len() == 1 {
+                ("is", "consider adding this import")
+            } else {
+                ("are", "consider adding these imports")
+            };
+            cx.tcx
+                .node_span_lint(RAYON_PRELUDE, hir_id, inject_use_span, |diag| {
+                    diag.primary_message(format!("{} {verb} not imported", imports.join(", ")));
+                    diag.span_suggestion(
+                        inject_use_span,
+                        help,
+                        import_suggestion,
+                        rustc_errors::Applicability::MachineApplicable,
+                    );
+                });
         }
     }
 }
 
-struct UseStatementVisitor {
-    has_import: bool,
+/// The traits among the names of a module.
+fn traits(children: &[ModChild]) -> impl Iterator<Item = DefId> + '_ {
+    children.iter().filter_map(|child| match child.res {
+        Res::Def(DefKind::Trait, def_id) => Some(def_id),
+        _ => None,
+    })
 }
 
-impl<'v> Visitor<'v> for UseStatementVisitor {
-    fn visit_item(&mut self, item: &'v Item<'v>) {
-        if let ItemKind::Use(use_path, UseKind::Glob) = item.kind {
-            let path: Vec<String> = use_path
-                .segments
-                .to_vec()
-                .iter()
-                .map(|x| x.ident.to_string())
-                .collect();
-
-            let trait_path = vec!["rayon".to_string(), "prelude".to_string()];
-            self.has_import |= trait_path == path
-        }
-        // Continue walking the rest of the tree
-        rustc_hir::intravisit::walk_item(self, item);
-    }
+/// The path under which the trait can be imported.
+fn trait_path(cx: &LateContext<'_>, def_id: DefId) -> String {
+    with_no_trimmed_paths!(cx.tcx.def_path_str(def_id))
 }
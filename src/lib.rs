@@ -10,8 +10,9 @@ extern crate rustc_session;
 #[allow(clippy::no_mangle_with_rust_abi)]
 #[no_mangle]
 pub fn register_lints(sess: &rustc_session::Session, lint_store: &mut rustc_lint::LintStore) {
-    // PHASE 0
-    rayon_imports::register_lints(sess, lint_store);
+    // reads the `dylint.toml` of the linted workspace, used by configurable lints
+    dylint_linting::init_config(sess);
+    // `cargo mate` applies the phases one after the other, see `cargo-mate/src/phases.rs`
     // PHASE 1
     //for_each::register_lints(sess, lint_store);
     to_iter::register_lints(sess, lint_store);
@@ -20,6 +21,17 @@ pub fn register_lints(sess: &rustc_session::Session, lint_store: &mut rustc_lint
     // PHASE 3
     fold::register_lints(sess, lint_store);
     // PHASE 4
-    par_fold::register_lints(sess, lint_store);
+    // before `par_fold`, whose reductions leave the chains `par_iter` reports to it
     par_iter::register_lints(sess, lint_store);
+    par_fold::register_lints(sess, lint_store);
+    par_join::register_lints(sess, lint_store);
+    par_sort::register_lints(sess, lint_store);
+    // PHASE 5: imports the prelude in the modules which got parallel suggestions above, and
+    // counts the ones skipped in crates without rayon
+    rayon_imports::register_lints(sess, lint_store);
+}
+
+#[test]
+fn ui() {
+    dylint_testing::ui_test_examples(env!("CARGO_PKG_NAME"));
 }
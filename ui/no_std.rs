@@ -0,0 +1,53 @@
+// run-rustfix
+#![no_std]
+#![allow(dead_code)]
+#![warn(rayon_dependency)]
+
+extern crate std;
+
+fn main() {}
+
+fn sum_products(n: u64) -> u64 {
+    let mut total = 0;
+    let mut i = 0;
+    while i < n {
+        let mut j = 0;
+        while j < n {
+            total += i * j;
+            j += 1;
+        }
+        i += 1;
+    }
+    total
+}
+
+fn sum_squares(n: u64) -> u64 {
+    let mut total = 0;
+    let mut i = 0;
+    while i < n {
+        let mut j = 0;
+        while j < n {
+            total += j * j;
+            j += 1;
+        }
+        i += 1;
+    }
+    total
+}
+
+// no: rayon needs `std`, the opportunity is only counted
+fn independent(x: u64, y: u64) -> u64 {
+    let a = sum_products(x);
+    let b = sum_squares(y);
+    a + b
+}
+
+// no: rayon needs `std`, the opportunity is only counted
+fn sum_of_squares(numbers: &[u64]) -> u64 {
+    numbers.iter().map(|x| x * x).sum()
+}
+
+// no: rayon needs `std`, the opportunity is only counted
+fn sort_numbers(numbers: &mut [u64]) {
+    numbers.sort_unstable();
+}
@@ -0,0 +1,96 @@
+// run-rustfix
+#![allow(dead_code, unused_imports)]
+
+fn main() {}
+
+// no: nothing is parallelized in the crate root
+fn count(ids: &[u64]) -> usize {
+    ids.len()
+}
+
+// should import the prelude for `par_iter`
+mod names {
+    pub fn names(ids: &[u64]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+}
+
+// should import the prelude for `reduce`
+mod sums {
+    pub fn sum() -> i32 {
+        let numbers = vec![1, 2, 3, 4, 5];
+        numbers.iter().map(|&num| num).fold(0, |mut sum, v| {
+            sum += v;
+            sum
+        })
+    }
+}
+
+// no: the prelude is already imported
+mod imported {
+    use rayon::prelude::*;
+
+    pub fn names(ids: &[u64]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+}
+
+// no: the traits are imported one by one
+mod listed {
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+    pub fn names(ids: &[u64]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+}
+
+// no: the traits are imported under other names
+mod renamed {
+    use rayon::prelude::{IntoParallelRefIterator as _, ParallelIterator as Parallel};
+
+    pub fn names(ids: &[u64]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+}
+
+mod prelude {
+    pub use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+}
+
+// no: the traits are re-exported by the crate
+mod reexported {
+    use crate::prelude::*;
+
+    pub fn names(ids: &[u64]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+}
+
+// no: the traits are inherited from the parent module
+mod parent {
+    use rayon::prelude::*;
+
+    mod child {
+        use super::*;
+
+        pub fn names(ids: &[u64]) -> Vec<String> {
+            ids.iter().map(|id| id.to_string()).collect()
+        }
+    }
+}
+
+// should import the missing trait only
+mod partial {
+    use rayon::iter::ParallelIterator;
+
+    pub fn names(ids: &[u64]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+}
+
+// should import `ParallelSliceMut` for `par_sort`
+mod sorted {
+    pub fn sort(ids: &mut [u64]) {
+        ids.sort();
+    }
+}
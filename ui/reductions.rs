@@ -0,0 +1,16 @@
+// run-rustfix
+#![allow(dead_code, unused_imports)]
+
+use rayon::prelude::*;
+
+fn main() {}
+
+// reported by `par_iter` alone, whose suggestion covers the reduction
+fn lengths(ids: &[u64]) -> usize {
+    ids.iter().map(|id| id.to_string().len()).sum()
+}
+
+// too cheap for `par_iter`, so reported as a reduction
+fn total(ids: &[u64]) -> u64 {
+    ids.iter().sum()
+}
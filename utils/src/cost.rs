@@ -0,0 +1,327 @@
+use clippy_utils::higher::{ForLoop, Range, VecArgs};
+use clippy_utils::macros::root_macro_call_first_node;
+use clippy_utils::visitors::for_each_expr;
+use clippy_utils::{path_to_local, path_to_local_id};
+use core::ops::ControlFlow;
+use rustc_ast::{LitKind, RangeLimits};
+use rustc_hir::def::DefKind;
+use rustc_hir::intravisit::{walk_expr, Visitor};
+use rustc_hir::{BindingMode, Expr, ExprKind, LoopSource, Mutability, Node, PatKind, QPath};
+use rustc_lint::LateContext;
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{TyKind, TypeckResults};
+
+/// Chains whose estimated cost is below this are cheaper to run sequentially.
+pub const DEFAULT_MIN_COST: u64 = 10_000;
+
+/// Threshold for chains bridged with `par_bridge`, whose items are pulled one at a time
+/// through a lock on the sequential iterator.
+pub const DEFAULT_BRIDGE_MIN_COST: u64 = 100_000;
+
+/// Number of iterations assumed when a length or a loop count isn't known.
+pub const UNKNOWN_LEN: u64 = 1000;
+
+/// How deep calls to the functions of the crate are followed.
+const MAX_CALL_DEPTH: usize = 3;
+
+const OP_COST: u64 = 1;
+const CALL_COST: u64 = 5;
+const EXPENSIVE_CALL_COST: u64 = 100;
+
+/// Functions and methods which allocate, format, do IO or heavy arithmetic.
+const EXPENSIVE_CALLS: &[&str] = &[
+    "clone",
+    "to_string",
+    "to_owned",
+    "to_vec",
+    "to_uppercase",
+    "to_lowercase",
+    "collect",
+    "sort",
+    "sort_unstable",
+    "sort_by",
+    "sort_by_key",
+    "powf",
+    "sqrt",
+    "exp",
+    "ln",
+    "sin",
+    "cos",
+    "tan",
+    "read",
+    "read_to_string",
+    "write",
+    "write_all",
+    "flush",
+    "sleep",
+    "lock",
+    "send",
+    "recv",
+];
+
+/// Macros which format or do IO.
+const EXPENSIVE_MACROS: &[&str] = &[
+    "format", "print", "println", "eprint", "eprintln", "write", "writeln", "dbg",
+];
+
+/// Estimates the work done by the iterator chain from `source`, the call producing
+/// the iterator, up to `top`: the number of items times the cost of the closures
+/// given to the adapters.
+#[must_use]
+pub fn chain_cost<'tcx>(
+    cx: &LateContext<'tcx>,
+    source: &'tcx Expr<'tcx>,
+    top: &'tcx Expr<'tcx>,
+) -> u64 {
+    let len = match source.kind {
+        ExprKind::MethodCall(_, recv, _, _) => known_len(cx, recv),
+        _ => None,
+    };
+
+    let mut per_item = 0;
+    let mut expr = top;
+    while expr.hir_id != source.hir_id
+        && let ExprKind::MethodCall(_, recv, args, _) = expr.kind
+    {
+        for arg in args {
+            if let ExprKind::Closure(closure) = arg.kind {
+                let body = cx.tcx.hir().body(closure.body);
+                per_item = work_cost(cx, body.value).saturating_add(per_item);
+            }
+        }
+        expr = recv;
+    }
+
+    len.unwrap_or(UNKNOWN_LEN).saturating_mul(per_item.max(1))
+}
+
+/// Estimates the cost of evaluating `expr` once.
+#[must_use]
+pub fn work_cost<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> u64 {
+    CostVisitor::new(cx, cx.typeck_results(), 0).cost_of(expr)
+}
+
+/// Estimates the cost of evaluating `expr` once like [`work_cost`], adding the cost of
+/// the bodies of the functions of the crate it calls instead of a flat cost per call.
+#[must_use]
+pub fn work_cost_with_calls<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> u64 {
+    CostVisitor::new(cx, cx.typeck_results(), MAX_CALL_DEPTH).cost_of(expr)
+}
+
+/// Returns the number of elements of `expr` when it is visible in the source: an
+/// array, a range with literal bounds, or a local initialized by one of these or
+/// by `vec![...]`. The initializer of a mutable local is only trusted if `expr` is
+/// the only other mention of it.
+#[must_use]
+pub fn known_len<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> Option<u64> {
+    known_len_in(cx, cx.typeck_results(), expr)
+}
+
+/// [`known_len`] of an expression of the body `typeck` belongs to.
+fn known_len_in<'tcx>(
+    cx: &LateContext<'tcx>,
+    typeck: &'tcx TypeckResults<'tcx>,
+    expr: &'tcx Expr<'tcx>,
+) -> Option<u64> {
+    if let TyKind::Array(_, len) = typeck.expr_ty(expr).peel_refs().kind()
+        && let Some(len) = len.try_eval_target_usize(cx.tcx, cx.param_env)
+    {
+        return Some(len);
+    }
+
+    let expr = local_init(cx, expr).unwrap_or(expr);
+    if let Some(range) = Range::hir(expr) {
+        let start = match range.start {
+            Some(start) => int_value(cx, start)?,
+            None => 0,
+        };
+        let end = int_value(cx, range.end?)?;
+        let len = end.saturating_sub(start);
+        return Some(match range.limits {
+            RangeLimits::HalfOpen => len,
+            RangeLimits::Closed => len.saturating_add(1),
+        });
+    }
+    match VecArgs::hir(cx, expr)? {
+        VecArgs::Vec(elems) => u64::try_from(elems.len()).ok(),
+        VecArgs::Repeat(_, len) => int_value(cx, len),
+    }
+}
+
+/// Returns the value of an integer literal, or of a local initialized by one.
+fn int_value<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> Option<u64> {
+    let expr = local_init(cx, expr).unwrap_or(expr);
+    if let ExprKind::Lit(lit) = expr.kind
+        && let LitKind::Int(n, _) = lit.node
+    {
+        u64::try_from(n.get()).ok()
+    } else {
+        None
+    }
+}
+
+fn local_init<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> Option<&'tcx Expr<'tcx>> {
+    let local_id = path_to_local(expr)?;
+    let Node::LetStmt(local) = cx.tcx.parent_hir_node(local_id) else {
+        return None;
+    };
+    // A mutable local may have been changed anywhere in its scope, even after `expr` in
+    // a loop.
+    if let PatKind::Binding(BindingMode(_, Mutability::Mut), ..) = local.pat.kind {
+        let (_, Node::Block(scope)) = cx
+            .tcx
+            .hir()
+            .parent_iter(local.hir_id)
+            .find(|(_, node)| matches!(node, Node::Block(_)))?
+        else {
+            return None;
+        };
+        let mentioned_elsewhere = for_each_expr(cx, scope, |e| {
+            if path_to_local_id(e, local_id) && e.hir_id != expr.hir_id {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        if mentioned_elsewhere.is_some() {
+            return None;
+        }
+    }
+    local.init
+}
+
+/// Adds up the cost of operators and calls, multiplying the bodies of loops by their
+/// number of iterations. Macro calls are counted as a single call. Calls to the
+/// functions of the crate are replaced by the cost of their bodies, `follow_calls`
+/// levels deep.
+struct CostVisitor<'a, 'tcx> {
+    cx: &'a LateContext<'tcx>,
+    /// The typeck results of the body being visited.
+    typeck: &'tcx TypeckResults<'tcx>,
+    follow_calls: usize,
+    cost: u64,
+}
+
+impl<'a, 'tcx> CostVisitor<'a, 'tcx> {
+    fn new(
+        cx: &'a LateContext<'tcx>,
+        typeck: &'tcx TypeckResults<'tcx>,
+        follow_calls: usize,
+    ) -> Self {
+        CostVisitor {
+            cx,
+            typeck,
+            follow_calls,
+            cost: 0,
+        }
+    }
+
+    fn cost_of(mut self, expr: &'tcx Expr<'tcx>) -> u64 {
+        self.visit_expr(expr);
+        self.cost
+    }
+
+    /// The cost of `expr`, a part of the same body, on its own.
+    fn nested(&self, expr: &'tcx Expr<'tcx>) -> u64 {
+        CostVisitor::new(self.cx, self.typeck, self.follow_calls).cost_of(expr)
+    }
+
+    fn add(&mut self, cost: u64) {
+        self.cost = self.cost.saturating_add(cost);
+    }
+
+    /// The cost of the body of the function of the crate `expr` calls, if it is followed.
+    fn callee_cost(&self, expr: &Expr<'_>) -> Option<u64> {
+        if self.follow_calls == 0 {
+            return None;
+        }
+        let def_id = match expr.kind {
+            ExprKind::MethodCall(..) => self.typeck.type_dependent_def_id(expr.hir_id)?,
+            ExprKind::Call(func, _) => match func.kind {
+                ExprKind::Path(ref qpath) => {
+                    self.typeck.qpath_res(qpath, func.hir_id).opt_def_id()?
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let local_id = def_id.as_local()?;
+        if !matches!(
+            self.cx.tcx.def_kind(local_id),
+            DefKind::Fn | DefKind::AssocFn
+        ) {
+            return None;
+        }
+        let (_, body_id) = self.cx.tcx.hir_node_by_def_id(local_id).associated_body()?;
+        let body = self.cx.tcx.hir().body(body_id);
+        Some(
+            CostVisitor::new(self.cx, self.cx.tcx.typeck(local_id), self.follow_calls - 1)
+                .cost_of(body.value),
+        )
+    }
+
+    fn call_cost(name: Option<&str>) -> u64 {
+        if name.is_some_and(|name| EXPENSIVE_CALLS.contains(&name)) {
+            EXPENSIVE_CALL_COST
+        } else {
+            CALL_COST
+        }
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for CostVisitor<'a, 'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.cx.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        if let Some(for_loop) = ForLoop::hir(expr) {
+            let iterations =
+                known_len_in(self.cx, self.typeck, for_loop.arg).unwrap_or(UNKNOWN_LEN);
+            let body = self.nested(for_loop.body);
+            self.add(self.nested(for_loop.arg));
+            self.add(iterations.saturating_mul(body));
+            return;
+        }
+        if let Some(macro_call) = root_macro_call_first_node(self.cx, expr) {
+            let name = self.cx.tcx.item_name(macro_call.def_id);
+            if EXPENSIVE_MACROS.contains(&name.as_str()) {
+                self.add(EXPENSIVE_CALL_COST);
+            } else {
+                self.add(CALL_COST);
+            }
+            return;
+        }
+
+        match expr.kind {
+            ExprKind::Loop(block, _, source, _) if source != LoopSource::ForLoop => {
+                let mut body = CostVisitor::new(self.cx, self.typeck, self.follow_calls);
+                body.visit_block(block);
+                self.add(UNKNOWN_LEN.saturating_mul(body.cost));
+                return;
+            }
+            _ if let Some(cost) = self.callee_cost(expr) => self.add(cost),
+            ExprKind::MethodCall(path, ..) => self.add(Self::call_cost(Some(path.ident.as_str()))),
+            ExprKind::Call(func, _) => {
+                let name = match func.kind {
+                    ExprKind::Path(QPath::Resolved(_, path)) => {
+                        path.segments.last().map(|s| s.ident.as_str())
+                    }
+                    ExprKind::Path(QPath::TypeRelative(_, segment)) => Some(segment.ident.as_str()),
+                    _ => None,
+                };
+                self.add(Self::call_cost(name));
+            }
+            ExprKind::Binary(..)
+            | ExprKind::Unary(..)
+            | ExprKind::Assign(..)
+            | ExprKind::AssignOp(..)
+            | ExprKind::Index(..)
+            | ExprKind::Cast(..) => self.add(OP_COST),
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
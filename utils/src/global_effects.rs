@@ -0,0 +1,213 @@
+use clippy_utils::def_path_def_ids;
+use clippy_utils::ty::{is_type_diagnostic_item, is_type_lang_item};
+use rustc_hash::FxHashSet;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{walk_body, walk_expr, Visitor};
+use rustc_hir::{self as hir, LangItem};
+use rustc_lint::LateContext;
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::adjustment::{Adjust, AutoBorrow, AutoBorrowMutability};
+use rustc_middle::ty::{self, Mutability, Ty, TypeckResults};
+use rustc_span::{sym, Symbol};
+
+/// How deep calls are followed, and local types are searched for cells.
+const MAX_DEPTH: usize = 8;
+
+/// An effect of a closure on state which is shared by all threads without
+/// synchronization, or which differs between threads.
+pub enum GlobalEffect {
+    /// Writes to a `static mut`.
+    StaticMut(Symbol),
+    /// Uses a static holding a `Cell`, `RefCell` or `UnsafeCell`.
+    CellStatic(Symbol),
+    /// Calls a foreign function, which may have any of the above effects.
+    ForeignCall(Symbol),
+    /// Uses a `thread_local!`, which has a separate value on each thread.
+    ThreadLocal(Symbol),
+}
+
+/// Finds the global effects of the closure `closure`, following calls to the functions
+/// of the crate.
+#[must_use]
+pub fn global_effects(cx: &LateContext<'_>, closure: LocalDefId) -> Vec<GlobalEffect> {
+    let Some((_, body_id)) = cx.tcx.hir_node_by_def_id(closure).associated_body() else {
+        return Vec::new();
+    };
+    let mut finder = EffectFinder {
+        cx,
+        typeck: cx.tcx.typeck(closure),
+        visited: FxHashSet::default(),
+        depth: 0,
+        effects: Vec::new(),
+    };
+    walk_body(&mut finder, cx.tcx.hir().body(body_id));
+    finder.effects
+}
+
+/// Finds the global effects of `expr`, an expression of the body being checked,
+/// following calls to the functions of the crate.
+#[must_use]
+pub fn expr_global_effects<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx hir::Expr<'tcx>,
+) -> Vec<GlobalEffect> {
+    let mut finder = EffectFinder {
+        cx,
+        typeck: cx.typeck_results(),
+        visited: FxHashSet::default(),
+        depth: 0,
+        effects: Vec::new(),
+    };
+    finder.visit_expr(expr);
+    finder.effects
+}
+
+struct EffectFinder<'a, 'tcx> {
+    cx: &'a LateContext<'tcx>,
+    typeck: &'tcx TypeckResults<'tcx>,
+    visited: FxHashSet<LocalDefId>,
+    depth: usize,
+    effects: Vec<GlobalEffect>,
+}
+
+impl<'a, 'tcx> EffectFinder<'a, 'tcx> {
+    /// Records a write through `place` if it is a `static mut`, or a projection of one.
+    fn check_write(&mut self, mut place: &hir::Expr<'_>) {
+        while let hir::ExprKind::Field(base, _) | hir::ExprKind::Index(base, _, _) = place.kind {
+            place = base;
+        }
+        if let hir::ExprKind::Path(ref qpath) = place.kind
+            && let Res::Def(
+                DefKind::Static {
+                    mutability: Mutability::Mut,
+                    ..
+                },
+                def_id,
+            ) = self.typeck.qpath_res(qpath, place.hir_id)
+        {
+            self.effects
+                .push(GlobalEffect::StaticMut(self.cx.tcx.item_name(def_id)));
+        }
+    }
+
+    fn check_path(&mut self, expr: &hir::Expr<'_>, qpath: &hir::QPath<'_>) {
+        let Some(def_id) = self.typeck.qpath_res(qpath, expr.hir_id).opt_def_id() else {
+            return;
+        };
+        if is_local_key(self.cx, self.typeck.expr_ty(expr)) {
+            self.effects
+                .push(GlobalEffect::ThreadLocal(self.cx.tcx.item_name(def_id)));
+        } else if let DefKind::Static {
+            mutability: Mutability::Not,
+            ..
+        } = self.cx.tcx.def_kind(def_id)
+            && has_cell(
+                self.cx,
+                self.cx.tcx.type_of(def_id).instantiate_identity(),
+                0,
+            )
+        {
+            self.effects
+                .push(GlobalEffect::CellStatic(self.cx.tcx.item_name(def_id)));
+        }
+    }
+
+    /// Records calls to foreign functions and looks for effects in the functions of the
+    /// crate. Trait methods are not resolved to their implementation.
+    fn check_callee(&mut self, def_id: DefId) {
+        if self.cx.tcx.is_foreign_item(def_id) {
+            self.effects
+                .push(GlobalEffect::ForeignCall(self.cx.tcx.item_name(def_id)));
+            return;
+        }
+        let Some(local_id) = def_id.as_local() else {
+            return;
+        };
+        if self.depth >= MAX_DEPTH
+            || !matches!(
+                self.cx.tcx.def_kind(local_id),
+                DefKind::Fn | DefKind::AssocFn
+            )
+            || !self.visited.insert(local_id)
+        {
+            return;
+        }
+        let Some((_, body_id)) = self.cx.tcx.hir_node_by_def_id(local_id).associated_body() else {
+            return;
+        };
+
+        let typeck = core::mem::replace(&mut self.typeck, self.cx.tcx.typeck(local_id));
+        self.depth += 1;
+        walk_body(self, self.cx.tcx.hir().body(body_id));
+        self.depth -= 1;
+        self.typeck = typeck;
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for EffectFinder<'a, 'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.cx.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        match expr.kind {
+            hir::ExprKind::Assign(place, _, _)
+            | hir::ExprKind::AssignOp(_, place, _)
+            | hir::ExprKind::AddrOf(_, Mutability::Mut, place) => self.check_write(place),
+            hir::ExprKind::MethodCall(_, recv, _, _) => {
+                let borrows_mut = self.typeck.expr_adjustments(recv).iter().any(|adjustment| {
+                    matches!(
+                        adjustment.kind,
+                        Adjust::Borrow(AutoBorrow::Ref(_, AutoBorrowMutability::Mut { .. }))
+                    )
+                });
+                if borrows_mut {
+                    self.check_write(recv);
+                }
+                if let Some(def_id) = self.typeck.type_dependent_def_id(expr.hir_id) {
+                    self.check_callee(def_id);
+                }
+            }
+            hir::ExprKind::Call(func, _) => {
+                if let hir::ExprKind::Path(ref qpath) = func.kind
+                    && let Some(def_id) = self.typeck.qpath_res(qpath, func.hir_id).opt_def_id()
+                {
+                    self.check_callee(def_id);
+                }
+            }
+            hir::ExprKind::Path(ref qpath) => self.check_path(expr, qpath),
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Checks whether `ty` is the `LocalKey` giving access to a `thread_local!`.
+fn is_local_key(cx: &LateContext<'_>, ty: Ty<'_>) -> bool {
+    if let ty::Adt(adt, _) = ty.kind() {
+        def_path_def_ids(cx.tcx, &["std", "thread", "LocalKey"]).any(|id| id == adt.did())
+    } else {
+        false
+    }
+}
+
+/// Checks whether `ty` holds a cell, directly, in its generic arguments, or in the
+/// fields of the types of the crate.
+fn has_cell<'tcx>(cx: &LateContext<'tcx>, ty: Ty<'tcx>, depth: usize) -> bool {
+    ty.walk().filter_map(|arg| arg.as_type()).any(|ty| {
+        let ty::Adt(adt, args) = ty.kind() else {
+            return false;
+        };
+        is_type_diagnostic_item(cx, ty, sym::Cell)
+            || is_type_diagnostic_item(cx, ty, sym::RefCell)
+            || is_type_lang_item(cx, ty, LangItem::UnsafeCell)
+            || (depth < MAX_DEPTH
+                && adt.did().is_local()
+                && adt
+                    .all_fields()
+                    .any(|field| has_cell(cx, field.ty(cx.tcx, args), depth + 1)))
+    })
+}
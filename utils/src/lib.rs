@@ -1,6 +1,9 @@
 #![feature(rustc_private)]
 #![allow(clippy::result_unit_err)]
+#![feature(unwrap_infallible)]
+#![feature(let_chains)]
 
+extern crate rustc_ast;
 extern crate rustc_driver;
 extern crate rustc_hash;
 extern crate rustc_hir;
@@ -11,6 +14,15 @@ extern crate rustc_middle;
 extern crate rustc_span;
 extern crate rustc_trait_selection;
 
+pub mod cost;
+pub mod global_effects;
+pub mod par_chains;
+pub mod par_modules;
+pub mod par_sources;
+pub mod rayon_crate;
+pub mod validator;
+pub mod variable_check;
+
 use rustc_hir::{Expr, ExprKind, Stmt, StmtKind};
 use rustc_span::source_map::SourceMap;
 use rustc_span::{Span, SyntaxContext};
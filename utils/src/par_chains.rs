@@ -0,0 +1,29 @@
+use std::sync::{Mutex, PoisonError};
+
+use rustc_hir::HirId;
+use rustc_lint::{LateContext, Level, Lint};
+
+/// The sources of the chains reported as a whole by a lint of a library, which the
+/// lints suggesting part of the same rewrite leave to it.
+static REPORTED: Mutex<Vec<HirId>> = Mutex::new(Vec::new());
+
+/// Records that `lint` reported the chain starting at the source `hir_id`. Nothing is
+/// recorded if the lint is allowed there.
+pub fn record_par_chain(cx: &LateContext<'_>, lint: &'static Lint, hir_id: HirId) {
+    if cx.tcx.lint_level_at_node(lint, hir_id).0 == Level::Allow {
+        return;
+    }
+    REPORTED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(hir_id);
+}
+
+/// Checks whether the chain starting at the source `hir_id` was already reported.
+#[must_use]
+pub fn is_par_chain_reported(hir_id: HirId) -> bool {
+    REPORTED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .contains(&hir_id)
+}
@@ -0,0 +1,96 @@
+use std::sync::{Mutex, PoisonError};
+
+use clippy_utils::get_trait_def_id;
+use rustc_hir::def_id::{DefId, LocalModDefId};
+use rustc_hir::HirId;
+use rustc_lint::{LateContext, Level, Lint};
+use rustc_span::Symbol;
+
+/// The traits of rayon's parallel iterators, in the order their methods are looked up.
+const ITER_TRAITS: &[&[&str]] = &[
+    &["rayon", "iter", "ParallelIterator"],
+    &["rayon", "iter", "IndexedParallelIterator"],
+    &["rayon", "iter", "IntoParallelIterator"],
+    &["rayon", "iter", "ParallelExtend"],
+    &["rayon", "iter", "ParallelBridge"],
+];
+
+/// A module of the crate being linted, and the traits whose methods the suggestions
+/// made in it call.
+pub struct ParModule {
+    pub module: LocalModDefId,
+    pub traits: Vec<DefId>,
+}
+
+/// A place edited by a suggestion, and the traits whose methods the new code calls.
+pub struct ParUse {
+    pub hir_id: HirId,
+    pub traits: Vec<DefId>,
+}
+
+/// Modules whose suggestions use rayon's traits. Shared by the lints of a library,
+/// which link a single copy of this crate.
+static PAR_MODULES: Mutex<Vec<ParModule>> = Mutex::new(Vec::new());
+
+/// Records that `lint`, emitted at `hir_id`, suggests the parallel code of `uses`, so
+/// that the traits it needs get imported in their modules. Nothing is recorded if the
+/// lint is allowed at `hir_id`.
+pub fn record_par_suggestion(
+    cx: &LateContext<'_>,
+    lint: &'static Lint,
+    hir_id: HirId,
+    uses: &[ParUse],
+) {
+    if cx.tcx.lint_level_at_node(lint, hir_id).0 == Level::Allow {
+        return;
+    }
+    let mut modules = PAR_MODULES.lock().unwrap_or_else(PoisonError::into_inner);
+    for par_use in uses {
+        let module = cx.tcx.parent_module(par_use.hir_id);
+        let index = if let Some(index) = modules.iter().position(|m| m.module == module) {
+            index
+        } else {
+            modules.push(ParModule {
+                module,
+                traits: Vec::new(),
+            });
+            modules.len() - 1
+        };
+        let traits = &mut modules[index].traits;
+        for &def_id in &par_use.traits {
+            if !traits.contains(&def_id) {
+                traits.push(def_id);
+            }
+        }
+    }
+}
+
+/// Takes the modules recorded so far, in the order they were first recorded.
+#[must_use]
+pub fn take_par_modules() -> Vec<ParModule> {
+    core::mem::take(&mut *PAR_MODULES.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// The traits of rayon's parallel iterators providing `methods`. Methods which none of
+/// them has, such as the sources of the iterators, are skipped.
+#[must_use]
+pub fn iter_method_traits(cx: &LateContext<'_>, methods: &[Symbol]) -> Vec<DefId> {
+    let iter_traits: Vec<DefId> = ITER_TRAITS
+        .iter()
+        .filter_map(|path| get_trait_def_id(cx.tcx, path))
+        .collect();
+    let mut traits = Vec::new();
+    for &method in methods {
+        if let Some(&def_id) = iter_traits.iter().find(|&&def_id| {
+            cx.tcx
+                .associated_items(def_id)
+                .filter_by_name_unhygienic(method)
+                .next()
+                .is_some()
+        }) && !traits.contains(&def_id)
+        {
+            traits.push(def_id);
+        }
+    }
+    traits
+}
@@ -0,0 +1,232 @@
+use clippy_utils::get_trait_def_id;
+use clippy_utils::ty::{is_type_diagnostic_item, is_type_lang_item};
+use rustc_hir::def_id::DefId;
+use rustc_hir::{Expr, ExprKind, LangItem};
+use rustc_infer::infer::TyCtxtInferExt;
+use rustc_lint::LateContext;
+use rustc_middle::ty::{self, GenericArgs, Ty};
+use rustc_span::{sym, Symbol, DUMMY_SP};
+use rustc_trait_selection::infer::InferCtxtExt;
+
+/// A sequential method producing an iterator, and the rayon method producing the
+/// corresponding parallel iterator.
+pub struct ParSource {
+    pub seq: &'static str,
+    pub par: &'static str,
+    /// The rayon trait providing `par`.
+    pub trait_path: &'static [&'static str],
+    /// Whether the method takes `self` by reference, in which case the trait is
+    /// implemented for the type behind the reference (`[T]`, `str`, `Vec<T>`, ...).
+    pub by_ref: bool,
+    /// Number of arguments of the call.
+    pub args: usize,
+    /// Whether the parallel iterator is indexed, for the iterators of other crates whose
+    /// type doesn't implement rayon's traits. `None` when the type is followed instead.
+    pub indexed: Option<bool>,
+    /// The methods of such an iterator, or all of rayon's if empty.
+    pub methods: &'static [&'static str],
+}
+
+const INTO_PAR_ITER: &[&str] = &["rayon", "iter", "IntoParallelIterator"];
+const INTO_PAR_REF_ITER: &[&str] = &["rayon", "iter", "IntoParallelRefIterator"];
+const INTO_PAR_REF_MUT_ITER: &[&str] = &["rayon", "iter", "IntoParallelRefMutIterator"];
+const PAR_SLICE: &[&str] = &["rayon", "slice", "ParallelSlice"];
+const PAR_SLICE_MUT: &[&str] = &["rayon", "slice", "ParallelSliceMut"];
+const PAR_STR: &[&str] = &["rayon", "str", "ParallelString"];
+const PAR_DRAIN_RANGE: &[&str] = &["rayon", "iter", "ParallelDrainRange"];
+
+impl ParSource {
+    const fn new(
+        seq: &'static str,
+        par: &'static str,
+        trait_path: &'static [&'static str],
+        by_ref: bool,
+        args: usize,
+    ) -> Self {
+        ParSource {
+            seq,
+            par,
+            trait_path,
+            by_ref,
+            args,
+            indexed: None,
+            methods: &[],
+        }
+    }
+}
+
+/// All sequential sources with a parallel equivalent.
+pub const PAR_SOURCES: &[ParSource] = &[
+    ParSource::new("into_iter", "into_par_iter", INTO_PAR_ITER, false, 0),
+    ParSource::new("iter", "par_iter", INTO_PAR_REF_ITER, true, 0),
+    ParSource::new("iter_mut", "par_iter_mut", INTO_PAR_REF_MUT_ITER, true, 0),
+    ParSource::new("chunks", "par_chunks", PAR_SLICE, true, 1),
+    ParSource::new("chunks_exact", "par_chunks_exact", PAR_SLICE, true, 1),
+    ParSource::new("windows", "par_windows", PAR_SLICE, true, 1),
+    ParSource::new("chunks_mut", "par_chunks_mut", PAR_SLICE_MUT, true, 1),
+    ParSource::new("bytes", "par_bytes", PAR_STR, true, 0),
+    ParSource::new("chars", "par_chars", PAR_STR, true, 0),
+    ParSource::new("char_indices", "par_char_indices", PAR_STR, true, 0),
+    ParSource::new("lines", "par_lines", PAR_STR, true, 0),
+    ParSource::new("split", "par_split", PAR_STR, true, 1),
+    ParSource::new("split_whitespace", "par_split_whitespace", PAR_STR, true, 0),
+    ParSource::new("drain", "par_drain", PAR_DRAIN_RANGE, true, 1),
+];
+
+/// Returns the names of all sequential source methods.
+#[must_use]
+pub fn seq_source_names() -> impl Iterator<Item = &'static str> {
+    PAR_SOURCES.iter().map(|s| s.seq)
+}
+
+/// A call to a sequential source method which can be replaced by its parallel
+/// counterpart.
+pub struct SourceCall<'tcx> {
+    pub source: &'static ParSource,
+    /// The rayon trait providing the parallel method.
+    pub trait_def_id: DefId,
+    /// The type implementing that trait.
+    pub self_ty: Ty<'tcx>,
+}
+
+/// If `expr` is a call to a sequential source method whose receiver implements the
+/// corresponding rayon trait, returns the mapping together with the trait.
+#[must_use]
+pub fn par_source<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx Expr<'tcx>,
+) -> Option<SourceCall<'tcx>> {
+    par_source_with(cx, expr, &[])
+}
+
+/// The same as [`par_source`], also trying the sources of `registered`, the parallel
+/// traits of other crates. They come first, so that they can stand in for rayon's.
+#[must_use]
+pub fn par_source_with<'tcx>(
+    cx: &LateContext<'tcx>,
+    expr: &'tcx Expr<'tcx>,
+    registered: &'static [ParSource],
+) -> Option<SourceCall<'tcx>> {
+    let ExprKind::MethodCall(path, recv, args, _) = expr.kind else {
+        return None;
+    };
+    registered
+        .iter()
+        .chain(PAR_SOURCES)
+        .filter(|s| path.ident.as_str() == s.seq && args.len() == s.args)
+        .find_map(|source| source_call(cx, recv, args, source))
+}
+
+fn source_call<'tcx>(
+    cx: &LateContext<'tcx>,
+    recv: &'tcx Expr<'tcx>,
+    args: &'tcx [Expr<'tcx>],
+    source: &'static ParSource,
+) -> Option<SourceCall<'tcx>> {
+    let trait_def_id = get_trait_def_id(cx.tcx, source.trait_path)?;
+
+    let typeck = cx.typeck_results();
+    // After auto-referencing, `v.iter()` is called on `&[T]` and `s.chars()` on `&str`.
+    let recv_ty = typeck.expr_ty_adjusted(recv);
+    let self_ty = if source.by_ref {
+        recv_ty.peel_refs()
+    } else {
+        recv_ty
+    };
+    if !implements_rayon_trait(cx, self_ty, trait_def_id) {
+        return None;
+    }
+
+    // rayon's patterns are only chars and char predicates, not strings.
+    if source.seq == "split" {
+        let pat_ty = typeck.expr_ty(&args[0]);
+        if !pat_ty.is_char() && !pat_ty.is_closure() {
+            return None;
+        }
+    }
+
+    Some(SourceCall {
+        source,
+        trait_def_id,
+        self_ty,
+    })
+}
+
+/// Checks whether `ty` implements the given rayon trait for some choice of the trait's
+/// other generic parameters, such as the element type of `ParallelSlice<T>`.
+#[must_use]
+pub fn implements_rayon_trait<'tcx>(
+    cx: &LateContext<'tcx>,
+    ty: Ty<'tcx>,
+    trait_def_id: DefId,
+) -> bool {
+    let infcx = cx.tcx.infer_ctxt().build();
+    let args = GenericArgs::for_item(cx.tcx, trait_def_id, |param, _| {
+        if param.index == 0 {
+            ty.into()
+        } else {
+            infcx.var_for_def(DUMMY_SP, param)
+        }
+    });
+    infcx
+        .type_implements_trait(trait_def_id, args, cx.param_env)
+        .may_apply()
+}
+
+/// Checks with `std`'s types alone whether `expr` is a call to a sequential source method
+/// which rayon would parallelize, for crates which don't depend on rayon and so don't load
+/// its traits. The opportunities found that way are only counted.
+#[must_use]
+pub fn std_source<'tcx>(cx: &LateContext<'tcx>, expr: &'tcx Expr<'tcx>) -> bool {
+    let ExprKind::MethodCall(path, recv, args, _) = expr.kind else {
+        return false;
+    };
+    let Some(source) = PAR_SOURCES
+        .iter()
+        .find(|s| path.ident.as_str() == s.seq && args.len() == s.args)
+    else {
+        return false;
+    };
+    // `Vec`s and arrays get to the methods of slices after auto-dereferencing.
+    let ty = cx.typeck_results().expr_ty_adjusted(recv).peel_refs();
+    let is_slice = matches!(ty.kind(), ty::Slice(_) | ty::Array(..));
+    let is_std = |items: &[Symbol]| {
+        items
+            .iter()
+            .any(|&item| is_type_diagnostic_item(cx, ty, item))
+    };
+    if source.trait_path == INTO_PAR_ITER || source.trait_path == INTO_PAR_REF_ITER {
+        is_slice
+            || is_std(&[
+                sym::Vec,
+                sym::VecDeque,
+                sym::LinkedList,
+                sym::BinaryHeap,
+                sym::HashMap,
+                sym::HashSet,
+                sym::BTreeMap,
+                sym::BTreeSet,
+                sym::Option,
+                sym::Result,
+            ])
+            || is_type_lang_item(cx, ty, LangItem::Range)
+            || is_type_lang_item(cx, ty, LangItem::RangeInclusiveStruct)
+    } else if source.trait_path == INTO_PAR_REF_MUT_ITER {
+        is_slice
+            || is_std(&[
+                sym::Vec,
+                sym::VecDeque,
+                sym::LinkedList,
+                sym::HashMap,
+                sym::BTreeMap,
+                sym::Option,
+                sym::Result,
+            ])
+    } else if source.trait_path == PAR_SLICE || source.trait_path == PAR_SLICE_MUT {
+        is_slice
+    } else if source.trait_path == PAR_STR {
+        ty.is_str()
+    } else {
+        is_std(&[sym::Vec, sym::VecDeque, sym::String])
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use clippy_utils::is_no_std_crate;
+use rustc_hir::HirId;
+use rustc_lint::{LateContext, Level, Lint, LintContext};
+use rustc_span::Symbol;
+
+/// Opportunities found by the lints of a library in a crate which can't use rayon.
+static SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Checks whether the crate can use rayon: it isn't `#![no_std]`, and rayon is one of its
+/// direct dependencies.
+#[must_use]
+pub fn crate_has_rayon(cx: &LateContext<'_>) -> bool {
+    if is_no_std_crate(cx) {
+        return false;
+    }
+    // Cargo hands the dependencies of the crate to rustc with `--extern`, which puts them in
+    // the extern prelude even before the crate names them.
+    if cx
+        .sess()
+        .opts
+        .externs
+        .get("rayon")
+        .is_some_and(|entry| entry.add_prelude)
+    {
+        return true;
+    }
+    // Otherwise an `extern crate rayon;` has loaded it from the library search paths.
+    let rayon = Symbol::intern("rayon");
+    cx.tcx.crates(()).iter().any(|&krate| {
+        cx.tcx.crate_name(krate) == rayon
+            && cx
+                .tcx
+                .extern_crate(krate.as_def_id())
+                .is_some_and(|extern_crate| extern_crate.is_direct())
+    })
+}
+
+/// Checks whether the opportunity found by `lint` at `hir_id` has to be skipped, because the
+/// crate can't use rayon. Skipped opportunities are counted, unless the lint is allowed at
+/// `hir_id`.
+#[must_use]
+pub fn skip_without_rayon(cx: &LateContext<'_>, lint: &'static Lint, hir_id: HirId) -> bool {
+    if crate_has_rayon(cx) {
+        return false;
+    }
+    if cx.tcx.lint_level_at_node(lint, hir_id).0 != Level::Allow {
+        SKIPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    true
+}
+
+/// Takes the number of opportunities skipped so far.
+#[must_use]
+pub fn take_skipped() -> usize {
+    SKIPPED.swap(0, Ordering::Relaxed)
+}
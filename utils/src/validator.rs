@@ -0,0 +1,78 @@
+use rustc_hir::intravisit::{walk_expr, Visitor};
+use rustc_hir::{self as hir};
+use rustc_lint::LateContext;
+use rustc_span::{sym, Symbol};
+
+use crate::global_effects::{global_effects, GlobalEffect};
+use crate::variable_check::{check_trait_impl, check_variables, is_type_valid};
+
+/// Checks that the values produced by method calls in an expression can be sent
+/// between threads and that closure arguments don't mutate or share captured state,
+/// nor global state such as a `static mut`.
+pub struct Validator<'a, 'tcx> {
+    pub cx: &'a LateContext<'tcx>,
+    pub is_valid: bool,
+    pub is_mut: bool,
+    /// A `thread_local!` used by a closure, whose value differs between threads.
+    pub thread_local: Option<Symbol>,
+    /// Method calls whose values only have to be `Send`, such as the sequential
+    /// iterators handed over to `par_bridge`, which are never shared.
+    pub send_only: hir::HirIdSet,
+}
+
+impl<'a, 'tcx> Validator<'a, 'tcx> {
+    #[must_use]
+    pub fn new(cx: &'a LateContext<'tcx>, is_mut: bool) -> Self {
+        Validator {
+            cx,
+            is_valid: true,
+            is_mut,
+            thread_local: None,
+            send_only: hir::HirIdSet::default(),
+        }
+    }
+}
+
+impl<'a, 'tcx> Visitor<'_> for Validator<'a, 'tcx> {
+    fn visit_expr(&mut self, ex: &hir::Expr) {
+        if let hir::ExprKind::MethodCall(_method_name, _receiver, args, _span) = ex.kind {
+            if !self.is_valid {
+                return;
+            }
+            let ex_ty = self.cx.typeck_results().expr_ty(ex);
+            self.is_valid &= if self.send_only.contains(&ex.hir_id) {
+                check_trait_impl(self.cx, ex_ty, sym::Send)
+            } else {
+                is_type_valid(self.cx, ex_ty)
+            };
+
+            for arg in args {
+                if let hir::ExprKind::Closure(closure) = arg.kind {
+                    let mut params = hir::HirIdSet::default();
+                    let body = self.cx.tcx.hir().body(closure.body);
+
+                    for param in body.params {
+                        if let hir::PatKind::Binding(_, hir_id, _, _) = param.pat.kind {
+                            params.insert(hir_id);
+                        }
+                    }
+
+                    self.is_valid &=
+                        check_variables(self.cx, closure.def_id, body, &params, self.is_mut);
+
+                    for effect in global_effects(self.cx, closure.def_id) {
+                        match effect {
+                            GlobalEffect::ThreadLocal(name) => {
+                                self.thread_local.get_or_insert(name);
+                            }
+                            GlobalEffect::StaticMut(_)
+                            | GlobalEffect::CellStatic(_)
+                            | GlobalEffect::ForeignCall(_) => self.is_valid = false,
+                        }
+                    }
+                }
+            }
+        }
+        walk_expr(self, ex)
+    }
+}